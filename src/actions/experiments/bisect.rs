@@ -0,0 +1,172 @@
+use crate::actions::{experiments::ExperimentError, Action, ActionsCtx};
+use crate::bisect::Bisection;
+use crate::db::QueryUtils;
+use crate::experiments::{Experiment, Status};
+use crate::prelude::*;
+use crate::report::{self, Comparison};
+use crate::results::{DatabaseDB, ReadResults};
+
+/// Queue the bisection of every crate that regressed between two dated nightlies.
+pub struct CreateBisection {
+    pub name: String,
+}
+
+impl Action for CreateBisection {
+    fn apply(self, ctx: &ActionsCtx) -> Fallible<()> {
+        let ex = match Experiment::get(&ctx.db, &self.name)? {
+            Some(ex) => ex,
+            None => return Err(ExperimentError::NotFound(self.name).into()),
+        };
+
+        // Results are only complete after the experiment finished running
+        if ex.status == Status::Queued || ex.status == Status::Running {
+            return Err(ExperimentError::CanOnlyBisectFinishedExperiments.into());
+        }
+
+        let db = DatabaseDB::new(&ctx.db);
+        let crates = ex.get_crates(&ctx.db)?;
+
+        let mut bisections = Vec::new();
+        for regression in report::find_regressions(&db, &ctx.config, &ex, &crates)? {
+            if regression.comparison != Comparison::Regressed {
+                continue;
+            }
+
+            let start_result =
+                match db.load_test_result(&ex, &regression.last_good, &regression.krate)? {
+                    Some(result) => result,
+                    None => continue,
+                };
+
+            if let Some(bisection) = Bisection::new(
+                regression.krate,
+                start_result,
+                regression.last_good,
+                regression.first_bad,
+            ) {
+                bisections.push(bisection);
+            }
+        }
+
+        if bisections.is_empty() {
+            return Err(ExperimentError::NothingToBisect(self.name).into());
+        }
+
+        ctx.db.transaction(|t| {
+            // Start the bisections from scratch if they were already created before
+            t.execute("DELETE FROM bisections WHERE experiment = ?1;", &[&ex.name])?;
+            t.execute(
+                "DELETE FROM bisection_results WHERE experiment = ?1;",
+                &[&ex.name],
+            )?;
+            t.execute(
+                "DELETE FROM experiments WHERE name IN \
+                 (SELECT experiment FROM bisection_probes WHERE parent = ?1);",
+                &[&ex.name],
+            )?;
+
+            for bisection in &bisections {
+                bisection.save(t, &ex)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CreateBisection;
+    use crate::actions::{Action, ActionsCtx, CreateExperiment, ExperimentError};
+    use crate::bisect::{Bisection, BisectionStatus};
+    use crate::config::Config;
+    use crate::crates::Crate;
+    use crate::db::Database;
+    use crate::experiments::{Experiment, Status};
+    use crate::results::{DatabaseDB, EncodingType, FailureReason, TestResult, WriteResults};
+
+    #[test]
+    fn test_create_bisection() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        let mut create = CreateExperiment::dummy("foo");
+        create.toolchains = vec![
+            "nightly-2019-03-01".parse().unwrap(),
+            "nightly-2019-03-09".parse().unwrap(),
+        ];
+        create.apply(&ctx).unwrap();
+
+        // Bisecting queued experiments is not allowed
+        let err = CreateBisection {
+            name: "foo".to_string(),
+        }
+        .apply(&ctx)
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&ExperimentError::CanOnlyBisectFinishedExperiments)
+        );
+
+        let mut ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        ex.set_status(&db, Status::NeedsReport).unwrap();
+
+        // Without any regression there is nothing to bisect
+        let err = CreateBisection {
+            name: "foo".to_string(),
+        }
+        .apply(&ctx)
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&ExperimentError::NothingToBisect("foo".into()))
+        );
+
+        let krate = Crate::Local("build-pass".into());
+        let results = DatabaseDB::new(&db);
+        for (tc, result) in ex.toolchains.iter().zip(&[
+            TestResult::TestPass,
+            TestResult::BuildFail(FailureReason::Unknown),
+        ]) {
+            results
+                .record_result(&ex, tc, &krate, None, &config, EncodingType::Plain, || {
+                    Ok(*result)
+                })
+                .unwrap();
+        }
+
+        CreateBisection {
+            name: "foo".to_string(),
+        }
+        .apply(&ctx)
+        .unwrap();
+
+        let bisections = Bisection::load_all(&db, &ex).unwrap();
+        assert_eq!(bisections.len(), 1);
+        assert_eq!(bisections[0].krate, krate);
+        assert_eq!(bisections[0].start_result, TestResult::TestPass);
+        assert_eq!(bisections[0].last_good, ex.toolchains[0]);
+        assert_eq!(bisections[0].first_bad, ex.toolchains[1]);
+        assert_eq!(bisections[0].status, BisectionStatus::Queued);
+    }
+
+    #[test]
+    fn test_bisect_missing_experiment() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        let err = CreateBisection {
+            name: "dummy".to_string(),
+        }
+        .apply(&ctx)
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&ExperimentError::NotFound("dummy".into()))
+        );
+    }
+}
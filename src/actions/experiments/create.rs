@@ -7,13 +7,18 @@ use chrono::Utc;
 
 pub struct CreateExperiment {
     pub name: String,
-    pub toolchains: [Toolchain; 2],
+    /// The toolchains to compare, in order. A single toolchain creates a baseline experiment.
+    pub toolchains: Vec<Toolchain>,
     pub mode: Mode,
     pub crates: CrateSelect,
     pub cap_lints: CapLints,
     pub priority: i32,
     pub github_issue: Option<GitHubIssue>,
     pub ignore_blacklist: bool,
+    /// Also build the crates with all their features and without the default ones.
+    pub feature_matrix: bool,
+    /// Targets to check the crates for, in addition to the host target.
+    pub targets: Vec<String>,
     pub assign: Option<Assignee>,
     pub requirement: Option<String>,
 }
@@ -25,13 +30,15 @@ impl CreateExperiment {
 
         CreateExperiment {
             name: name.to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             crates: CrateSelect::Local,
             cap_lints: CapLints::Forbid,
             priority: 0,
             github_issue: None,
             ignore_blacklist: false,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: None,
         }
@@ -45,26 +52,27 @@ impl Action for CreateExperiment {
             return Err(ExperimentError::AlreadyExists(self.name.clone()).into());
         }
 
-        // Ensure no experiment with duplicate toolchains is created
-        if self.toolchains[0] == self.toolchains[1] {
-            return Err(ExperimentError::DuplicateToolchains.into());
-        }
+        super::validate_toolchains(&self.toolchains)?;
+        super::validate_targets(&self.targets)?;
 
-        let crates = crate::crates::lists::get_crates(self.crates, &ctx.db, &ctx.config)?;
+        let select = self.crates.with_seed();
+        let crates_seed = select.seed();
+        let shas = crate::crates::lists::pinned_shas(&select)?;
+        let snapshots = crate::crates::lists::latest_snapshots(&select, &ctx.db)?;
+        let crates = crate::crates::lists::get_crates(select, &ctx.db, &ctx.config)?;
 
         ctx.db.transaction(|transaction| {
             transaction.execute(
                 "INSERT INTO experiments \
-                 (name, mode, cap_lints, toolchain_start, toolchain_end, priority, created_at, \
-                 status, github_issue, github_issue_url, github_issue_number, ignore_blacklist, \
-                 assigned_to, requirement) \
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14);",
+                 (name, mode, cap_lints, toolchains, priority, created_at, status, \
+                 github_issue, github_issue_url, github_issue_number, ignore_blacklist, \
+                 assigned_to, requirement, crates_seed, feature_matrix, targets) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16);",
                 &[
                     &self.name,
                     &self.mode.to_str(),
                     &self.cap_lints.to_str(),
-                    &self.toolchains[0].to_string(),
-                    &self.toolchains[1].to_string(),
+                    &super::serialize_toolchains(&self.toolchains)?,
                     &self.priority,
                     &Utc::now(),
                     &Status::Queued.to_str(),
@@ -74,6 +82,9 @@ impl Action for CreateExperiment {
                     &self.ignore_blacklist,
                     &self.assign.map(|a| a.to_string()),
                     &self.requirement,
+                    &crates_seed,
+                    &self.feature_matrix,
+                    &::serde_json::to_string(&self.targets)?,
                 ],
             )?;
 
@@ -85,6 +96,21 @@ impl Action for CreateExperiment {
                 )?;
             }
 
+            for (krate, sha) in &shas {
+                transaction.execute(
+                    "INSERT INTO shas (experiment, crate, sha) VALUES (?1, ?2, ?3);",
+                    &[&self.name, &::serde_json::to_string(&krate)?, &sha],
+                )?;
+            }
+
+            for snapshot in &snapshots {
+                transaction.execute(
+                    "INSERT INTO experiment_list_snapshots (experiment, snapshot) \
+                     VALUES (?1, ?2);",
+                    &[&self.name, &snapshot.id],
+                )?;
+            }
+
             Ok(())
         })?;
 
@@ -97,11 +123,12 @@ mod tests {
     use super::CreateExperiment;
     use crate::actions::{Action, ActionsCtx, ExperimentError};
     use crate::config::{Config, CrateConfig};
-    use crate::crates::Crate;
+    use crate::crates::{lists::ListSnapshot, Crate, GitHubRepo, GitRepo, RegistryCrate};
     use crate::db::{Database, QueryUtils};
     use crate::experiments::{
         Assignee, CapLints, CrateSelect, Experiment, GitHubIssue, Mode, Status,
     };
+    use crate::results::{DatabaseDB, EncodingType, FailureReason, TestResult, WriteResults};
     use crate::toolchain::{MAIN_TOOLCHAIN, TEST_TOOLCHAIN};
 
     #[test]
@@ -117,7 +144,7 @@ mod tests {
 
         CreateExperiment {
             name: "foo".to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             crates: CrateSelect::Local,
             cap_lints: CapLints::Forbid,
@@ -128,6 +155,8 @@ mod tests {
                 number: 10,
             }),
             ignore_blacklist: true,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: Some("linux".to_string()),
         }
@@ -138,8 +167,9 @@ mod tests {
         assert_eq!(ex.name.as_str(), "foo");
         assert_eq!(
             ex.toolchains,
-            [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()]
+            vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()]
         );
+        assert!(!ex.is_baseline());
         assert_eq!(ex.mode, Mode::BuildAndTest);
         assert_eq!(
             ex.get_crates(&ctx.db).unwrap(),
@@ -157,6 +187,10 @@ mod tests {
         assert!(ex.assigned_to.is_none());
         assert!(ex.ignore_blacklist);
         assert_eq!(ex.requirement, Some("linux".to_string()));
+
+        let snapshots = ListSnapshot::of_experiment(&db, "foo").unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].list, "local");
     }
 
     #[test]
@@ -247,13 +281,15 @@ mod tests {
         // Ensure an experiment with duplicate toolchains can't be created
         let err = CreateExperiment {
             name: "foo".to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), MAIN_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), MAIN_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             crates: CrateSelect::Local,
             cap_lints: CapLints::Forbid,
             priority: 0,
             github_issue: None,
             ignore_blacklist: false,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: None,
         }
@@ -266,6 +302,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_baseline() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        CreateExperiment {
+            toolchains: vec![MAIN_TOOLCHAIN.clone()],
+            ..CreateExperiment::dummy("foo")
+        }
+        .apply(&ctx)
+        .unwrap();
+
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        assert_eq!(ex.toolchains, vec![MAIN_TOOLCHAIN.clone()]);
+        assert!(ex.is_baseline());
+
+        // Experiments without toolchains can't be created
+        let err = CreateExperiment {
+            toolchains: Vec::new(),
+            ..CreateExperiment::dummy("bar")
+        }
+        .apply(&ctx)
+        .unwrap_err();
+
+        assert_eq!(err.downcast_ref(), Some(&ExperimentError::NoToolchains));
+    }
+
+    #[test]
+    fn test_multiple_toolchains() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        let toolchains = vec![
+            MAIN_TOOLCHAIN.clone(),
+            TEST_TOOLCHAIN.clone(),
+            "nightly".parse().unwrap(),
+            "try#0000000000000000000000000000000000000000"
+                .parse()
+                .unwrap(),
+        ];
+        CreateExperiment {
+            toolchains: toolchains.clone(),
+            ..CreateExperiment::dummy("foo")
+        }
+        .apply(&ctx)
+        .unwrap();
+
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        assert_eq!(ex.toolchains, toolchains);
+
+        // Duplicates are rejected even if the toolchains aren't adjacent
+        let err = CreateExperiment {
+            toolchains: vec![
+                MAIN_TOOLCHAIN.clone(),
+                TEST_TOOLCHAIN.clone(),
+                MAIN_TOOLCHAIN.clone(),
+            ],
+            ..CreateExperiment::dummy("bar")
+        }
+        .apply(&ctx)
+        .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref(),
+            Some(&ExperimentError::DuplicateToolchains)
+        );
+    }
+
     #[test]
     fn test_duplicate_name() {
         let db = Database::temp().unwrap();
@@ -277,13 +387,15 @@ mod tests {
         // The first experiment can be created successfully
         CreateExperiment {
             name: "foo".to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             crates: CrateSelect::Local,
             cap_lints: CapLints::Forbid,
             priority: 0,
             github_issue: None,
             ignore_blacklist: false,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: None,
         }
@@ -293,13 +405,15 @@ mod tests {
         // While the second one fails
         let err = CreateExperiment {
             name: "foo".to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             crates: CrateSelect::Local,
             cap_lints: CapLints::Forbid,
             priority: 0,
             github_issue: None,
             ignore_blacklist: false,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: None,
         }
@@ -311,4 +425,108 @@ mod tests {
             Some(&ExperimentError::AlreadyExists("foo".into()))
         );
     }
+
+    #[test]
+    fn test_regressed_crates_of_experiment() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        CreateExperiment::dummy("foo").apply(&ctx).unwrap();
+        let mut foo = Experiment::get(&db, "foo").unwrap().unwrap();
+
+        // Unfinished experiments can't be used to select crates
+        let mut create = CreateExperiment::dummy("bar");
+        create.crates = CrateSelect::Regressed("foo".into());
+        assert!(create.apply(&ctx).is_err());
+
+        let results = DatabaseDB::new(&db);
+        let regressed = Crate::Local("beta-regression".into());
+        for krate in foo.get_crates(&db).unwrap() {
+            for tc in &foo.toolchains {
+                let result = if krate == regressed && *tc == *TEST_TOOLCHAIN {
+                    TestResult::BuildFail(FailureReason::Unknown)
+                } else {
+                    TestResult::TestPass
+                };
+                results
+                    .record_result(&foo, tc, &krate, None, &config, EncodingType::Plain, || {
+                        Ok(result)
+                    })
+                    .unwrap();
+            }
+        }
+        foo.set_status(&db, Status::NeedsReport).unwrap();
+
+        let mut create = CreateExperiment::dummy("bar");
+        create.crates = CrateSelect::Regressed("foo".into());
+        create.apply(&ctx).unwrap();
+
+        let bar = Experiment::get(&db, "bar").unwrap().unwrap();
+        assert_eq!(bar.get_crates(&db).unwrap(), vec![regressed]);
+    }
+
+    #[test]
+    fn test_explicit_crates_list() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        let mut create = CreateExperiment::dummy("foo");
+        create.crates =
+            "list:build-pass,lazy_static-1.0.0,https://github.com/rust-lang/crater#f00,\
+                         git+https://gitlab.com/foo/bar#v1.0"
+                .parse()
+                .unwrap();
+        create.apply(&ctx).unwrap();
+
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        assert_eq!(
+            ex.get_crates(&db).unwrap(),
+            vec![
+                Crate::Registry(RegistryCrate {
+                    name: "lazy_static".into(),
+                    version: "1.0.0".into(),
+                }),
+                Crate::GitHub(GitHubRepo {
+                    org: "rust-lang".into(),
+                    name: "crater".into(),
+                }),
+                Crate::Local("build-pass".into()),
+                Crate::Git(GitRepo {
+                    url: "https://gitlab.com/foo/bar".into(),
+                    rev: Some("v1.0".into()),
+                }),
+            ]
+        );
+
+        // GitHub repos can be pinned to a commit
+        let crater = Crate::GitHub(GitHubRepo {
+            org: "rust-lang".into(),
+            name: "crater".into(),
+        });
+        assert_eq!(
+            DatabaseDB::new(&db).get_sha(&ex, &crater).unwrap(),
+            Some("f00".into())
+        );
+
+        // Crates that can't be parsed are rejected
+        let mut create = CreateExperiment::dummy("bar");
+        create.crates = CrateSelect::List(vec!["invalid".into()]);
+        assert!(create.apply(&ctx).is_err());
+
+        // Git URLs and revisions looking like options are rejected
+        for spec in &[
+            "git+--upload-pack=touch /tmp/pwned",
+            "git+https://gitlab.com/foo/bar#--all",
+        ] {
+            let mut create = CreateExperiment::dummy("bar");
+            create.crates = CrateSelect::List(vec![spec.to_string()]);
+            assert!(create.apply(&ctx).is_err());
+        }
+    }
 }
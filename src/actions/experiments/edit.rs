@@ -6,12 +6,20 @@ use crate::toolchain::Toolchain;
 
 pub struct EditExperiment {
     pub name: String,
-    pub toolchains: [Option<Toolchain>; 2],
+    /// Replace all the toolchains of the experiment.
+    pub toolchains: Option<Vec<Toolchain>>,
+    /// Replace the first toolchain of the experiment.
+    pub start: Option<Toolchain>,
+    /// Replace the last toolchain of the experiment, or add it to a baseline experiment.
+    pub end: Option<Toolchain>,
     pub crates: Option<CrateSelect>,
     pub mode: Option<Mode>,
     pub cap_lints: Option<CapLints>,
     pub priority: Option<i32>,
     pub ignore_blacklist: Option<bool>,
+    pub feature_matrix: Option<bool>,
+    /// Replace the targets the crates are checked for.
+    pub targets: Option<Vec<String>>,
     pub assign: Option<Assignee>,
     pub requirement: Option<String>,
 }
@@ -21,12 +29,16 @@ impl EditExperiment {
     pub fn dummy(name: &str) -> Self {
         EditExperiment {
             name: name.to_string(),
-            toolchains: [None, None],
+            toolchains: None,
+            start: None,
+            end: None,
             mode: None,
             crates: None,
             cap_lints: None,
             priority: None,
             ignore_blacklist: None,
+            feature_matrix: None,
+            targets: None,
             assign: None,
             requirement: None,
         }
@@ -46,22 +58,35 @@ impl Action for EditExperiment {
         }
 
         ctx.db.transaction(|t| {
-            // Try to update both toolchains
-            for (i, col) in ["toolchain_start", "toolchain_end"].iter().enumerate() {
-                if let Some(tc) = self.toolchains[i].take() {
-                    ex.toolchains[i] = tc;
-
-                    // Ensure no duplicate toolchain is inserted
-                    if ex.toolchains[0] == ex.toolchains[1] {
-                        return Err(ExperimentError::DuplicateToolchains.into());
+            // Try to update the toolchains
+            if self.toolchains.is_some() || self.start.is_some() || self.end.is_some() {
+                if let Some(toolchains) = self.toolchains.take() {
+                    ex.toolchains = toolchains;
+                }
+                if let Some(start) = self.start.take() {
+                    if ex.toolchains.is_empty() {
+                        ex.toolchains.push(start);
+                    } else {
+                        ex.toolchains[0] = start;
+                    }
+                }
+                if let Some(end) = self.end.take() {
+                    // Setting the end toolchain of a baseline experiment turns it into a
+                    // comparison between the two toolchains
+                    if ex.toolchains.len() < 2 {
+                        ex.toolchains.push(end);
+                    } else {
+                        *ex.toolchains.last_mut().unwrap() = end;
                     }
-
-                    let changes = t.execute(
-                        &format!("UPDATE experiments SET {} = ?1 WHERE name = ?2;", col),
-                        &[&ex.toolchains[i].to_string(), &self.name],
-                    )?;
-                    assert_eq!(changes, 1);
                 }
+
+                super::validate_toolchains(&ex.toolchains)?;
+
+                let changes = t.execute(
+                    "UPDATE experiments SET toolchains = ?1 WHERE name = ?2;",
+                    &[&super::serialize_toolchains(&ex.toolchains)?, &self.name],
+                )?;
+                assert_eq!(changes, 1);
             }
 
             // Try to update the ignore_blacklist field
@@ -75,9 +100,57 @@ impl Action for EditExperiment {
                 ex.ignore_blacklist = ignore_blacklist;
             }
 
+            // Try to update the feature_matrix field
+            if let Some(feature_matrix) = self.feature_matrix {
+                let changes = t.execute(
+                    "UPDATE experiments SET feature_matrix = ?1 WHERE name = ?2;",
+                    &[&feature_matrix, &self.name],
+                )?;
+                assert_eq!(changes, 1);
+                ex.feature_matrix = feature_matrix;
+            }
+
+            // Try to update the targets
+            if let Some(targets) = self.targets {
+                super::validate_targets(&targets)?;
+                let changes = t.execute(
+                    "UPDATE experiments SET targets = ?1 WHERE name = ?2;",
+                    &[&::serde_json::to_string(&targets)?, &self.name],
+                )?;
+                assert_eq!(changes, 1);
+                ex.targets = targets;
+            }
+
             // Try to update the list of crates
             // This is also done if ignore_blacklist is changed to recalculate the skipped crates
             let new_crates = if let Some(crates) = self.crates {
+                let crates = crates.with_seed();
+                let changes = t.execute(
+                    "UPDATE experiments SET crates_seed = ?1 WHERE name = ?2;",
+                    &[&crates.seed(), &self.name],
+                )?;
+                assert_eq!(changes, 1);
+                ex.crates_seed = crates.seed();
+
+                for (krate, sha) in crate::crates::lists::pinned_shas(&crates)? {
+                    t.execute(
+                        "INSERT INTO shas (experiment, crate, sha) VALUES (?1, ?2, ?3);",
+                        &[&self.name, &::serde_json::to_string(&krate)?, &sha],
+                    )?;
+                }
+
+                t.execute(
+                    "DELETE FROM experiment_list_snapshots WHERE experiment = ?1;",
+                    &[&self.name],
+                )?;
+                for snapshot in crate::crates::lists::latest_snapshots(&crates, &ctx.db)? {
+                    t.execute(
+                        "INSERT INTO experiment_list_snapshots (experiment, snapshot) \
+                         VALUES (?1, ?2);",
+                        &[&self.name, &snapshot.id],
+                    )?;
+                }
+
                 Some(crate::crates::lists::get_crates(
                     crates,
                     &ctx.db,
@@ -197,13 +270,18 @@ mod tests {
         // Create an experiment with the data we're going to change
         CreateExperiment {
             name: "foo".to_string(),
-            toolchains: ["stable".parse().unwrap(), "beta".parse().unwrap()],
+            toolchains: vec!["stable".parse().unwrap(), "beta".parse().unwrap()],
             mode: Mode::BuildAndTest,
-            crates: CrateSelect::SmallRandom,
+            crates: CrateSelect::Random {
+                count: 20,
+                seed: None,
+            },
             cap_lints: CapLints::Forbid,
             priority: 0,
             github_issue: None,
             ignore_blacklist: false,
+            feature_matrix: false,
+            targets: Vec::new(),
             assign: None,
             requirement: None,
         }
@@ -213,15 +291,16 @@ mod tests {
         // Change everything!
         EditExperiment {
             name: "foo".to_string(),
-            toolchains: [
-                Some("nightly-1970-01-01".parse().unwrap()),
-                Some("nightly-1970-01-02".parse().unwrap()),
-            ],
+            toolchains: None,
+            start: Some("nightly-1970-01-01".parse().unwrap()),
+            end: Some("nightly-1970-01-02".parse().unwrap()),
             mode: Some(Mode::CheckOnly),
             crates: Some(CrateSelect::Local),
             cap_lints: Some(CapLints::Warn),
             priority: Some(10),
             ignore_blacklist: Some(true),
+            feature_matrix: Some(true),
+            targets: Some(vec!["wasm32-unknown-unknown".into()]),
             assign: Some(Assignee::CLI),
             requirement: Some("windows".to_string()),
         }
@@ -237,6 +316,8 @@ mod tests {
         assert_eq!(ex.cap_lints, CapLints::Warn);
         assert_eq!(ex.priority, 10);
         assert_eq!(ex.ignore_blacklist, true);
+        assert!(ex.feature_matrix);
+        assert_eq!(ex.targets, vec!["wasm32-unknown-unknown".to_string()]);
         assert_eq!(ex.assigned_to, Some(Assignee::CLI));
         assert_eq!(ex.requirement, Some("windows".to_string()));
 
@@ -321,12 +402,12 @@ mod tests {
 
         // First create an experiment
         let mut dummy = CreateExperiment::dummy("foo");
-        dummy.toolchains = [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()];
+        dummy.toolchains = vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()];
         dummy.apply(&ctx).unwrap();
 
         // Then try to switch the second toolchain to MAIN_TOOLCHAIN
         let mut edit = EditExperiment::dummy("foo");
-        edit.toolchains[1] = Some(MAIN_TOOLCHAIN.clone());
+        edit.end = Some(MAIN_TOOLCHAIN.clone());
 
         let err = edit.apply(&ctx).unwrap_err();
         assert_eq!(
@@ -335,6 +416,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_edit_baseline() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        // First create a baseline experiment
+        let mut dummy = CreateExperiment::dummy("foo");
+        dummy.toolchains = vec![MAIN_TOOLCHAIN.clone()];
+        dummy.apply(&ctx).unwrap();
+
+        // Then add an end toolchain to it
+        let mut edit = EditExperiment::dummy("foo");
+        edit.end = Some(TEST_TOOLCHAIN.clone());
+        edit.apply(&ctx).unwrap();
+
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        assert!(!ex.is_baseline());
+        assert_eq!(
+            ex.toolchains,
+            vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()]
+        );
+    }
+
+    #[test]
+    fn test_edit_toolchains_list() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        CreateExperiment::dummy("foo").apply(&ctx).unwrap();
+
+        // Replace the toolchains, and then only the last one of them
+        let mut edit = EditExperiment::dummy("foo");
+        edit.toolchains = Some(vec![
+            MAIN_TOOLCHAIN.clone(),
+            TEST_TOOLCHAIN.clone(),
+            "nightly".parse().unwrap(),
+        ]);
+        edit.apply(&ctx).unwrap();
+
+        let mut edit = EditExperiment::dummy("foo");
+        edit.end = Some("nightly-1970-01-01".parse().unwrap());
+        edit.apply(&ctx).unwrap();
+
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        assert_eq!(
+            ex.toolchains,
+            vec![
+                MAIN_TOOLCHAIN.clone(),
+                TEST_TOOLCHAIN.clone(),
+                "nightly-1970-01-01".parse().unwrap(),
+            ]
+        );
+
+        // Removing every toolchain is not allowed
+        let mut edit = EditExperiment::dummy("foo");
+        edit.toolchains = Some(Vec::new());
+        let err = edit.apply(&ctx).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&ExperimentError::NoToolchains));
+    }
+
     #[test]
     fn test_editing_missing_experiment() {
         let db = Database::temp().unwrap();
@@ -1,11 +1,17 @@
+mod bisect;
 mod create;
 mod delete;
 mod edit;
 
+pub use self::bisect::CreateBisection;
 pub use self::create::CreateExperiment;
 pub use self::delete::DeleteExperiment;
 pub use self::edit::EditExperiment;
 
+use crate::prelude::*;
+use crate::toolchain::Toolchain;
+use std::collections::HashSet;
+
 #[derive(Debug, failure::Fail)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 pub enum ExperimentError {
@@ -13,8 +19,56 @@ pub enum ExperimentError {
     NotFound(String),
     #[fail(display = "experiment '{}' already exists", _0)]
     AlreadyExists(String),
+    #[fail(display = "no toolchains provided")]
+    NoToolchains,
     #[fail(display = "duplicate toolchains provided")]
     DuplicateToolchains,
+    #[fail(display = "invalid target: '{}'", _0)]
+    InvalidTarget(String),
+    #[fail(display = "duplicate targets provided")]
+    DuplicateTargets,
     #[fail(display = "it's only possible to edit queued experiments")]
     CanOnlyEditQueuedExperiments,
+    #[fail(display = "it's only possible to bisect finished experiments")]
+    CanOnlyBisectFinishedExperiments,
+    #[fail(
+        display = "experiment '{}' has no regressions between dated nightlies",
+        _0
+    )]
+    NothingToBisect(String),
+}
+
+fn validate_toolchains(toolchains: &[Toolchain]) -> Fallible<()> {
+    if toolchains.is_empty() {
+        return Err(ExperimentError::NoToolchains.into());
+    }
+
+    let mut seen = HashSet::new();
+    if !toolchains.iter().all(|tc| seen.insert(tc)) {
+        return Err(ExperimentError::DuplicateToolchains.into());
+    }
+
+    Ok(())
+}
+
+fn validate_targets(targets: &[String]) -> Fallible<()> {
+    let mut seen = HashSet::new();
+    for target in targets {
+        if target.is_empty() || target.contains(|c: char| c.is_whitespace() || c == '+') {
+            return Err(ExperimentError::InvalidTarget(target.clone()).into());
+        }
+        if !seen.insert(target) {
+            return Err(ExperimentError::DuplicateTargets.into());
+        }
+    }
+
+    Ok(())
+}
+
+fn serialize_toolchains(toolchains: &[Toolchain]) -> Fallible<String> {
+    let names = toolchains
+        .iter()
+        .map(|tc| tc.to_string())
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string(&names)?)
 }
@@ -0,0 +1,83 @@
+use crate::actions::{Action, ActionsCtx};
+use crate::db::QueryUtils;
+use crate::prelude::*;
+use std::fs::File;
+use std::path::PathBuf;
+
+/// Load the download counts of the crates.io crates from the `crates.csv` file of a database
+/// dump, to rank the crates by popularity.
+pub struct LoadDownloads {
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct DumpCrate {
+    name: String,
+    downloads: i64,
+}
+
+impl Action for LoadDownloads {
+    fn apply(self, ctx: &ActionsCtx) -> Fallible<()> {
+        let file = File::open(&self.path)
+            .with_context(|_| format!("failed to open {}", self.path.display()))?;
+        let mut reader = ::csv::Reader::from_reader(file);
+
+        let mut count = 0;
+        ctx.db.transaction(|t| {
+            t.execute("DELETE FROM crate_downloads;", &[])?;
+            for krate in reader.deserialize() {
+                let krate: DumpCrate = krate?;
+                t.execute_cached(
+                    "INSERT INTO crate_downloads (crate, downloads) VALUES (?1, ?2);",
+                    &[&krate.name, &krate.downloads],
+                )?;
+                count += 1;
+            }
+
+            Ok(())
+        })?;
+
+        info!("loaded the download counts of {} crates", count);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LoadDownloads;
+    use crate::actions::{Action, ActionsCtx};
+    use crate::config::Config;
+    use crate::db::{Database, QueryUtils};
+    use std::io::Write;
+
+    #[test]
+    fn test_load_downloads() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        let mut dump = tempfile::NamedTempFile::new().unwrap();
+        writeln!(dump, "created_at,downloads,id,name").unwrap();
+        writeln!(dump, "2015-01-01 00:00:00,1000,1,lazy_static").unwrap();
+        writeln!(dump, "2015-01-01 00:00:00,10,2,foo").unwrap();
+        dump.flush().unwrap();
+
+        LoadDownloads {
+            path: dump.path().into(),
+        }
+        .apply(&ctx)
+        .unwrap();
+
+        let downloads: Vec<(String, i64)> = db
+            .query(
+                "SELECT * FROM crate_downloads ORDER BY downloads DESC;",
+                &[],
+                |row| (row.get("crate"), row.get("downloads")),
+            )
+            .unwrap();
+        assert_eq!(
+            downloads,
+            vec![("lazy_static".into(), 1000), ("foo".into(), 10)]
+        );
+    }
+}
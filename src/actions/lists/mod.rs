@@ -1,3 +1,7 @@
+mod downloads;
+mod prune;
 mod update;
 
+pub use self::downloads::LoadDownloads;
+pub use self::prune::PruneLists;
 pub use self::update::UpdateLists;
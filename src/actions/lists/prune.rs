@@ -0,0 +1,97 @@
+use crate::actions::{Action, ActionsCtx};
+use crate::crates::ListSnapshot;
+use crate::db::QueryUtils;
+use crate::prelude::*;
+use std::collections::HashMap;
+
+/// Delete the old snapshots of the lists of crates, as every update stores a full copy of the
+/// lists. The snapshots used by experiments are kept, so that they can still be compared.
+pub struct PruneLists {
+    /// How many of the latest snapshots of each list to keep.
+    pub keep: usize,
+}
+
+impl Action for PruneLists {
+    fn apply(self, ctx: &ActionsCtx) -> Fallible<()> {
+        // The latest snapshots are the current lists, used to create new experiments
+        if self.keep == 0 {
+            bail!("at least the latest snapshot of each list must be kept");
+        }
+
+        let mut kept: HashMap<String, usize> = HashMap::new();
+        let mut pruned = Vec::new();
+        for snapshot in ListSnapshot::all(&ctx.db, None)?.into_iter().rev() {
+            let kept = kept.entry(snapshot.list.clone()).or_insert(0);
+            let used = ctx.db.exists(
+                "SELECT rowid FROM experiment_list_snapshots WHERE snapshot = ?1;",
+                &[&snapshot.id],
+            )?;
+
+            if *kept < self.keep || used {
+                *kept += 1;
+            } else {
+                pruned.push(snapshot);
+            }
+        }
+
+        ctx.db.transaction(|t| {
+            for snapshot in &pruned {
+                // This also deletes the crates of the snapshot, thanks to the foreign keys
+                t.execute("DELETE FROM list_snapshots WHERE id = ?1;", &[&snapshot.id])?;
+            }
+
+            Ok(())
+        })?;
+
+        info!("pruned {} snapshots of the lists", pruned.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PruneLists;
+    use crate::actions::{Action, ActionsCtx, CreateExperiment};
+    use crate::config::Config;
+    use crate::crates::ListSnapshot;
+    use crate::db::Database;
+
+    #[test]
+    fn test_prune_lists() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        // The first snapshot is used by an experiment
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+        CreateExperiment::dummy("foo").apply(&ctx).unwrap();
+        for _ in 0..3 {
+            crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+        }
+
+        let ids = |db: &Database| {
+            ListSnapshot::all(db, None)
+                .unwrap()
+                .iter()
+                .map(|s| s.id)
+                .collect::<Vec<_>>()
+        };
+        let all = ids(&db);
+        assert_eq!(all.len(), 4);
+
+        assert!(PruneLists { keep: 0 }.apply(&ctx).is_err());
+        assert_eq!(ids(&db), all);
+
+        PruneLists { keep: 2 }.apply(&ctx).unwrap();
+        assert_eq!(ids(&db), vec![all[0], all[2], all[3]]);
+
+        PruneLists { keep: 1 }.apply(&ctx).unwrap();
+        assert_eq!(ids(&db), vec![all[0], all[3]]);
+        assert!(!ListSnapshot::get(&db, all[3])
+            .unwrap()
+            .unwrap()
+            .crates(&db)
+            .unwrap()
+            .is_empty());
+    }
+}
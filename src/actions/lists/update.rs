@@ -1,11 +1,14 @@
 use crate::actions::{Action, ActionsCtx};
-use crate::crates::lists::{GitHubList, List, LocalList, RegistryList};
+use crate::crates::lists::{
+    AltRegistryList, GitHubList, List, LocalList, RegistryList, RegistryVersionsList,
+};
 use crate::prelude::*;
 
 pub struct UpdateLists {
     pub github: bool,
     pub registry: bool,
     pub local: bool,
+    pub alt_registries: bool,
 }
 
 impl Default for UpdateLists {
@@ -14,6 +17,7 @@ impl Default for UpdateLists {
             github: true,
             registry: true,
             local: true,
+            alt_registries: true,
         }
     }
 }
@@ -22,12 +26,17 @@ impl Action for UpdateLists {
     fn apply(self, ctx: &ActionsCtx) -> Fallible<()> {
         if self.github {
             info!("updating GitHub repositories list");
-            GitHubList::default().update(&ctx.db)?;
+            GitHubList::new(ctx.config).update(&ctx.db)?;
         }
 
         if self.registry {
             info!("updating crates.io crates list");
-            RegistryList.update(&ctx.db)?;
+            let registry = RegistryList::new(ctx.config);
+            registry.update(&ctx.db)?;
+            registry.update_dependencies(&ctx.db)?;
+
+            info!("updating crates.io release lines list");
+            RegistryVersionsList::new(ctx.config).update(&ctx.db)?;
         }
 
         if self.local {
@@ -35,6 +44,11 @@ impl Action for UpdateLists {
             LocalList::default().update(&ctx.db)?;
         }
 
+        if self.alt_registries {
+            info!("updating alternative registries list");
+            AltRegistryList::new(ctx.config).update(&ctx.db)?;
+        }
+
         Ok(())
     }
 }
@@ -1,8 +1,8 @@
 use crate::agent::Capabilities;
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::results::TestResult;
+use crate::results::{DiagnosticCount, FutureIncompat, StepMetrics, TestResult, UnstableFeature};
 use crate::server::api_types::{AgentConfig, ApiResponse, CraterToken};
 use crate::toolchain::Toolchain;
 use crate::utils;
@@ -130,15 +130,15 @@ impl AgentApi {
         })
     }
 
-    pub fn next_experiment(&self) -> Fallible<(Experiment, Vec<Crate>)> {
+    pub fn next_experiment(&self) -> Fallible<(Experiment, Vec<Crate>, Vec<(Crate, String)>)> {
         self.retry(|this| loop {
             let resp: Option<_> = this
                 .build_request(Method::GET, "next-experiment")
                 .send()?
                 .to_api_response()?;
 
-            if let Some((experiment, crates)) = resp {
-                return Ok((experiment, crates));
+            if let Some((experiment, crates, shas)) = resp {
+                return Ok((experiment, crates, shas));
             }
 
             ::std::thread::sleep(::std::time::Duration::from_secs(RETRY_AFTER));
@@ -152,7 +152,12 @@ impl AgentApi {
         toolchain: &Toolchain,
         log: &[u8],
         result: TestResult,
-        shas: &[(GitHubRepo, String)],
+        attempts: u32,
+        metrics: &[StepMetrics],
+        diagnostics: &[DiagnosticCount],
+        future_incompats: &[FutureIncompat],
+        unstable_features: &[UnstableFeature],
+        shas: &[(Crate, String)],
     ) -> Fallible<()> {
         self.retry(|this| {
             let _: bool = this
@@ -165,6 +170,11 @@ impl AgentApi {
                             "toolchain": toolchain,
                             "result": result,
                             "log": base64::encode(log),
+                            "attempts": attempts,
+                            "metrics": metrics,
+                            "diagnostics": diagnostics,
+                            "future_incompats": future_incompats,
+                            "unstable_features": unstable_features,
                         },
                     ],
                     "shas": shas,
@@ -85,7 +85,7 @@ impl Agent {
         })
     }
 
-    fn experiment(&self) -> Fallible<(Experiment, Vec<Crate>)> {
+    fn experiment(&self) -> Fallible<(Experiment, Vec<Crate>, Vec<(Crate, String)>)> {
         info!("asking the server for a new experiment...");
         Ok(self.api.next_experiment()?)
     }
@@ -108,7 +108,8 @@ fn run_experiment(
     db: &ResultsUploader,
     threads_count: usize,
 ) -> Result<(), (Option<Experiment>, Error)> {
-    let (ex, crates) = agent.experiment().map_err(|e| (None, e))?;
+    let (ex, crates, shas) = agent.experiment().map_err(|e| (None, e))?;
+    db.set_pinned_shas(shas);
     crate::runner::run_ex(&ex, workspace, &crates, db, threads_count, &agent.config)
         .map_err(|err| (Some(ex), err))?;
     Ok(())
@@ -1,18 +1,28 @@
 use crate::agent::api::AgentApi;
 use crate::config::Config;
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::results::{EncodingType, TestResult, WriteResults};
+use crate::results::{
+    DiagnosticCount, EncodingType, FutureIncompat, StepMetrics, TestResult, UnstableFeature,
+    WriteResults,
+};
 use crate::toolchain::Toolchain;
 use rustwide::logging::{self, LogStorage};
+use std::collections::HashMap;
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct ResultsUploader<'a> {
     api: &'a AgentApi,
-    shas: Arc<Mutex<Vec<(GitHubRepo, String)>>>,
+    shas: Arc<Mutex<Vec<(Crate, String)>>>,
+    pinned_shas: Arc<Mutex<HashMap<Crate, String>>>,
+    attempts: Arc<Mutex<HashMap<(Crate, Toolchain), u32>>>,
+    metrics: Arc<Mutex<HashMap<(Crate, Toolchain), Vec<StepMetrics>>>>,
+    diagnostics: Arc<Mutex<HashMap<(Crate, Toolchain), Vec<DiagnosticCount>>>>,
+    future_incompats: Arc<Mutex<HashMap<(Crate, Toolchain), Vec<FutureIncompat>>>>,
+    unstable_features: Arc<Mutex<HashMap<(Crate, Toolchain), Vec<UnstableFeature>>>>,
 }
 
 impl<'a> ResultsUploader<'a> {
@@ -20,8 +30,19 @@ impl<'a> ResultsUploader<'a> {
         ResultsUploader {
             api,
             shas: Arc::new(Mutex::new(Vec::new())),
+            pinned_shas: Arc::new(Mutex::new(HashMap::new())),
+            attempts: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            future_incompats: Arc::new(Mutex::new(HashMap::new())),
+            unstable_features: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+
+    /// Set the shas git crates were pinned to by previous runs of the experiment.
+    pub fn set_pinned_shas(&self, shas: Vec<(Crate, String)>) {
+        *self.pinned_shas.lock().unwrap() = shas.into_iter().collect();
+    }
 }
 
 impl<'a> WriteResults for ResultsUploader<'a> {
@@ -35,11 +56,89 @@ impl<'a> WriteResults for ResultsUploader<'a> {
         Ok(None)
     }
 
-    fn record_sha(&self, _ex: &Experiment, repo: &GitHubRepo, sha: &str) -> Fallible<()> {
+    fn get_sha(&self, _ex: &Experiment, krate: &Crate) -> Fallible<Option<String>> {
+        Ok(self.pinned_shas.lock().unwrap().get(krate).cloned())
+    }
+
+    fn record_sha(&self, _ex: &Experiment, krate: &Crate, sha: &str) -> Fallible<()> {
+        self.pinned_shas
+            .lock()
+            .unwrap()
+            .insert(krate.clone(), sha.to_string());
         self.shas
             .lock()
             .unwrap()
-            .push((repo.clone(), sha.to_string()));
+            .push((krate.clone(), sha.to_string()));
+        Ok(())
+    }
+
+    fn record_attempts(
+        &self,
+        _ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        attempts: u32,
+    ) -> Fallible<()> {
+        self.attempts
+            .lock()
+            .unwrap()
+            .insert((krate.clone(), toolchain.clone()), attempts);
+        Ok(())
+    }
+
+    fn record_metrics(
+        &self,
+        _ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        metrics: &[StepMetrics],
+    ) -> Fallible<()> {
+        self.metrics
+            .lock()
+            .unwrap()
+            .insert((krate.clone(), toolchain.clone()), metrics.to_vec());
+        Ok(())
+    }
+
+    fn record_diagnostics(
+        &self,
+        _ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        diagnostics: &[DiagnosticCount],
+    ) -> Fallible<()> {
+        self.diagnostics
+            .lock()
+            .unwrap()
+            .insert((krate.clone(), toolchain.clone()), diagnostics.to_vec());
+        Ok(())
+    }
+
+    fn record_future_incompats(
+        &self,
+        _ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        future_incompats: &[FutureIncompat],
+    ) -> Fallible<()> {
+        self.future_incompats.lock().unwrap().insert(
+            (krate.clone(), toolchain.clone()),
+            future_incompats.to_vec(),
+        );
+        Ok(())
+    }
+
+    fn record_unstable_features(
+        &self,
+        _ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        features: &[UnstableFeature],
+    ) -> Fallible<()> {
+        self.unstable_features
+            .lock()
+            .unwrap()
+            .insert((krate.clone(), toolchain.clone()), features.to_vec());
         Ok(())
     }
 
@@ -61,10 +160,51 @@ impl<'a> WriteResults for ResultsUploader<'a> {
         let output = storage.to_string();
 
         let shas = ::std::mem::replace(self.shas.lock().unwrap().deref_mut(), Vec::new());
+        let attempts = self
+            .attempts
+            .lock()
+            .unwrap()
+            .remove(&(krate.clone(), toolchain.clone()))
+            .unwrap_or(1);
+        let metrics = self
+            .metrics
+            .lock()
+            .unwrap()
+            .remove(&(krate.clone(), toolchain.clone()))
+            .unwrap_or_default();
+        let diagnostics = self
+            .diagnostics
+            .lock()
+            .unwrap()
+            .remove(&(krate.clone(), toolchain.clone()))
+            .unwrap_or_default();
+        let future_incompats = self
+            .future_incompats
+            .lock()
+            .unwrap()
+            .remove(&(krate.clone(), toolchain.clone()))
+            .unwrap_or_default();
+        let unstable_features = self
+            .unstable_features
+            .lock()
+            .unwrap()
+            .remove(&(krate.clone(), toolchain.clone()))
+            .unwrap_or_default();
 
         info!("sending results to the crater server...");
-        self.api
-            .record_progress(ex, krate, toolchain, output.as_bytes(), result, &shas)?;
+        self.api.record_progress(
+            ex,
+            krate,
+            toolchain,
+            output.as_bytes(),
+            result,
+            attempts,
+            &metrics,
+            &diagnostics,
+            &future_incompats,
+            &unstable_features,
+            &shas,
+        )?;
 
         Ok(result)
     }
@@ -69,8 +69,14 @@ load_files! {
         "ui/500.html",
 
         "report/layout.html",
+        "report/causes.html",
+        "report/diagnostics.html",
         "report/downloads.html",
+        "report/future-incompat.html",
+        "report/perf.html",
         "report/results.html",
+        "report/roots.html",
+        "report/unstable-features.html",
     ],
     assets: [
         "ui.css" => mime::TEXT_CSS,
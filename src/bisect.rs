@@ -0,0 +1,652 @@
+use crate::config::Config;
+use crate::crates::Crate;
+use crate::db::{Database, QueryUtils};
+use crate::experiments::{Experiment, Status};
+use crate::prelude::*;
+use crate::report::{self, Comparison};
+use crate::results::{
+    DatabaseDB, DiagnosticCount, EncodedLog, EncodingType, FutureIncompat, ReadResults,
+    StepMetrics, TestResult, UnstableFeature, WriteResults,
+};
+use crate::runner;
+use crate::toolchain::Toolchain;
+use crate::utils;
+use chrono::{Duration, NaiveDate, Utc};
+use rustwide::logging::{self, LogStorage};
+use rustwide::{Toolchain as RustwideToolchain, Workspace};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+string_enum!(pub enum BisectionStatus {
+    Queued => "queued",
+    Completed => "completed",
+    Failed => "failed",
+});
+
+/// Extract the date of a `nightly-YYYY-MM-DD` toolchain.
+pub(crate) fn nightly_date(tc: &Toolchain) -> Option<NaiveDate> {
+    match tc.source {
+        RustwideToolchain::Dist { ref name } if name.starts_with("nightly-") => {
+            NaiveDate::parse_from_str(&name["nightly-".len()..], "%Y-%m-%d").ok()
+        }
+        _ => None,
+    }
+}
+
+/// The nightly of a date, with the same flags as another toolchain.
+fn nightly(date: NaiveDate, flags_of: &Toolchain) -> Toolchain {
+    Toolchain {
+        source: RustwideToolchain::Dist {
+            name: Cow::Owned(format!("nightly-{}", date.format("%Y-%m-%d"))),
+        },
+        rustflags: flags_of.rustflags.clone(),
+        features: flags_of.features,
+        target: flags_of.target.clone(),
+        ci_try: false,
+    }
+}
+
+pub struct Bisection {
+    pub krate: Crate,
+    /// The result of the crate before it regressed.
+    pub start_result: TestResult,
+    pub last_good: Toolchain,
+    pub first_bad: Toolchain,
+    pub status: BisectionStatus,
+}
+
+impl Bisection {
+    /// Prepare the bisection of a regression between two dated nightlies, returning `None` if
+    /// the toolchains can't be bisected.
+    pub fn new(
+        krate: Crate,
+        start_result: TestResult,
+        last_good: Toolchain,
+        first_bad: Toolchain,
+    ) -> Option<Self> {
+        let good = nightly_date(&last_good)?;
+        let bad = nightly_date(&first_bad)?;
+        if good >= bad
+            || last_good.rustflags != first_bad.rustflags
+            || last_good.features != first_bad.features
+            || last_good.target != first_bad.target
+        {
+            return None;
+        }
+
+        Some(Bisection {
+            krate,
+            start_result,
+            last_good,
+            first_bad,
+            status: BisectionStatus::Queued,
+        })
+    }
+
+    pub fn load_all(db: &Database, ex: &Experiment) -> Fallible<Vec<Bisection>> {
+        db.query(
+            "SELECT * FROM bisections WHERE experiment = ?1;",
+            &[&ex.name],
+            |row| -> Fallible<Bisection> {
+                Ok(Bisection {
+                    krate: serde_json::from_str(&row.get::<_, String>("crate"))?,
+                    start_result: row.get::<_, String>("start_result").parse()?,
+                    last_good: row.get::<_, String>("last_good").parse()?,
+                    first_bad: row.get::<_, String>("first_bad").parse()?,
+                    status: row.get::<_, String>("status").parse()?,
+                })
+            },
+        )?
+        .into_iter()
+        .collect()
+    }
+
+    pub(crate) fn save(&self, db: &impl QueryUtils, ex: &Experiment) -> Fallible<()> {
+        db.execute(
+            "INSERT INTO bisections \
+             (experiment, crate, start_result, last_good, first_bad, status) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+            &[
+                &ex.name,
+                &serde_json::to_string(&self.krate)?,
+                &self.start_result.to_string(),
+                &self.last_good.to_string(),
+                &self.first_bad.to_string(),
+                &self.status.to_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The nightly halfway between the last good and the first bad ones, or `None` if they are
+    /// consecutive and the bisection is done.
+    fn next_probe(&self) -> Option<Toolchain> {
+        let good = nightly_date(&self.last_good)?;
+        let bad = nightly_date(&self.first_bad)?;
+
+        let days = bad.signed_duration_since(good).num_days();
+        if days <= 1 {
+            None
+        } else {
+            Some(nightly(good + Duration::days(days / 2), &self.first_bad))
+        }
+    }
+
+    fn record_probe(&mut self, config: &Config, probe: Toolchain, result: TestResult) {
+        match report::compare(config, &self.krate, Some(self.start_result), Some(result)) {
+            Comparison::Regressed => self.first_bad = probe,
+            Comparison::Fixed
+            | Comparison::SameBuildFail
+            | Comparison::SameTestFail
+            | Comparison::SameTestSkipped
+            | Comparison::SameTestPass => self.last_good = probe,
+            // Spurious failures, errors and broken crates don't tell on which side of the
+            // regression the nightly is
+            _ => self.status = BisectionStatus::Failed,
+        }
+    }
+}
+
+/// Stores the results of the nightlies tested during a bisection separately from the results of
+/// the experiment.
+pub struct BisectionDB<'a> {
+    db: &'a Database,
+}
+
+impl<'a> BisectionDB<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        BisectionDB { db }
+    }
+}
+
+impl<'a> WriteResults for BisectionDB<'a> {
+    fn get_result(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Option<TestResult>> {
+        let result: Option<String> = self
+            .db
+            .query(
+                "SELECT result FROM bisection_results \
+                 WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+                 LIMIT 1;",
+                &[
+                    &ex.name,
+                    &toolchain.to_string(),
+                    &serde_json::to_string(krate)?,
+                ],
+                |row| row.get("result"),
+            )?
+            .pop();
+
+        if let Some(res) = result {
+            Ok(Some(res.parse()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn get_sha(&self, ex: &Experiment, krate: &Crate) -> Fallible<Option<String>> {
+        // Bisect the same code that was tested in the experiment
+        DatabaseDB::new(self.db).get_sha(ex, krate)
+    }
+
+    fn record_sha(&self, _ex: &Experiment, _krate: &Crate, _sha: &str) -> Fallible<()> {
+        // The shas were already recorded while running the experiment
+        Ok(())
+    }
+
+    fn record_attempts(
+        &self,
+        _ex: &Experiment,
+        _toolchain: &Toolchain,
+        _krate: &Crate,
+        _attempts: u32,
+    ) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn record_metrics(
+        &self,
+        _ex: &Experiment,
+        _toolchain: &Toolchain,
+        _krate: &Crate,
+        _metrics: &[StepMetrics],
+    ) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn record_diagnostics(
+        &self,
+        _ex: &Experiment,
+        _toolchain: &Toolchain,
+        _krate: &Crate,
+        _diagnostics: &[DiagnosticCount],
+    ) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn record_future_incompats(
+        &self,
+        _ex: &Experiment,
+        _toolchain: &Toolchain,
+        _krate: &Crate,
+        _future_incompats: &[FutureIncompat],
+    ) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn record_unstable_features(
+        &self,
+        _ex: &Experiment,
+        _toolchain: &Toolchain,
+        _krate: &Crate,
+        _features: &[UnstableFeature],
+    ) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn record_result<F>(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        existing_logs: Option<LogStorage>,
+        config: &Config,
+        encoding_type: EncodingType,
+        f: F,
+    ) -> Fallible<TestResult>
+    where
+        F: FnOnce() -> Fallible<TestResult>,
+    {
+        let storage = existing_logs.unwrap_or_else(|| LogStorage::from(config));
+        let result = logging::capture(&storage, f)?;
+        let log = EncodedLog::from_plain_slice(storage.to_string().as_bytes(), encoding_type)?;
+
+        self.db.execute(
+            "INSERT INTO bisection_results \
+             (experiment, crate, toolchain, result, log, encoding) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+            &[
+                &ex.name,
+                &serde_json::to_string(krate)?,
+                &toolchain.to_string(),
+                &result.to_string(),
+                &log.as_slice(),
+                &log.get_encoding_type().to_str(),
+            ],
+        )?;
+        Ok(result)
+    }
+}
+
+/// Mark the queued bisections without nightlies left to test as completed, and group the other
+/// ones by the nightly they need to test next.
+fn next_round(
+    db: &Database,
+    ex: &Experiment,
+    bisections: &mut [Bisection],
+) -> Fallible<HashMap<Toolchain, Vec<usize>>> {
+    let mut round: HashMap<Toolchain, Vec<usize>> = HashMap::new();
+    for (i, bisection) in bisections.iter_mut().enumerate() {
+        if bisection.status != BisectionStatus::Queued {
+            continue;
+        }
+
+        if let Some(probe) = bisection.next_probe() {
+            round.entry(probe).or_insert_with(Vec::new).push(i);
+        } else {
+            bisection.status = BisectionStatus::Completed;
+            bisection.save(db, ex)?;
+        }
+    }
+    Ok(round)
+}
+
+/// Run the queued bisections of an experiment until all of them are done.
+///
+/// Bisections are executed in rounds, each one testing the nightly halfway through the
+/// remaining range of every crate. Crates that need the same nightly in a round are tested
+/// together, reusing the normal tasks graph of the runner.
+pub fn run_bisections(
+    ex: &Experiment,
+    workspace: &Workspace,
+    db: &Database,
+    threads_count: usize,
+    config: &Config,
+) -> Fallible<Vec<Bisection>> {
+    let results = BisectionDB::new(db);
+
+    loop {
+        let mut bisections = Bisection::load_all(db, ex)?;
+
+        let round = next_round(db, ex, &mut bisections)?;
+        if round.is_empty() {
+            return Ok(bisections);
+        }
+
+        for (probe, indexes) in round {
+            // The probe already has the flags of the regressed toolchain
+            let probe_ex = Experiment {
+                toolchains: vec![probe.clone()],
+                feature_matrix: false,
+                targets: Vec::new(),
+                ..ex.clone()
+            };
+            let crates = indexes
+                .iter()
+                .map(|&i| bisections[i].krate.clone())
+                .collect::<Vec<_>>();
+
+            info!("bisecting {} crates on {}", crates.len(), probe);
+            let run = runner::run_ex(
+                &probe_ex,
+                workspace,
+                &crates,
+                &results,
+                threads_count,
+                config,
+            );
+            if let Err(err) = &run {
+                // Only the crates needing this nightly can't be bisected any further
+                error!("failed to test the crates on {}", probe);
+                utils::report_failure(err);
+            }
+
+            for i in indexes {
+                let bisection = &mut bisections[i];
+                match results.get_result(&probe_ex, &probe, &bisection.krate)? {
+                    Some(result) if run.is_ok() => {
+                        bisection.record_probe(config, probe.clone(), result)
+                    }
+                    _ => bisection.status = BisectionStatus::Failed,
+                }
+                bisection.save(db, ex)?;
+            }
+        }
+    }
+}
+
+/// Find the experiment whose bisections are advanced by a probe experiment, if it's one.
+pub fn probe_parent(db: &Database, ex: &Experiment) -> Fallible<Option<Experiment>> {
+    let parent: Option<String> = db.get_row(
+        "SELECT parent FROM bisection_probes WHERE experiment = ?1;",
+        &[&ex.name],
+        |row| row.get("parent"),
+    )?;
+
+    match parent {
+        Some(parent) => Experiment::get(db, &parent),
+        None => Ok(None),
+    }
+}
+
+/// Queue the next round of the bisections of an experiment on the server. Each nightly of the
+/// round is tested by a probe experiment, executed by the agents like any other experiment.
+/// Returns whether any probe was queued, or if all the bisections are done.
+pub fn queue_probes(db: &Database, ex: &Experiment) -> Fallible<bool> {
+    let mut bisections = Bisection::load_all(db, ex)?;
+    let round = next_round(db, ex, &mut bisections)?;
+    if round.is_empty() {
+        return Ok(false);
+    }
+
+    let queued: u32 = db
+        .get_row(
+            "SELECT COUNT(*) AS count FROM bisection_probes WHERE parent = ?1;",
+            &[&ex.name],
+            |row| row.get("count"),
+        )?
+        .unwrap();
+
+    // Experiments created by the users can already have the names of the probes
+    let mut index = queued;
+    let mut probes = Vec::new();
+    for (probe, indexes) in round {
+        let name = loop {
+            index += 1;
+            let name = format!("{}-bisect-{}", ex.name, index);
+            if !Experiment::exists(db, &name)? {
+                break name;
+            }
+        };
+        probes.push((name, probe, indexes));
+    }
+
+    db.transaction(|t| {
+        for (name, probe, indexes) in probes {
+            info!(
+                "queueing {} to bisect {} crates on {}",
+                name,
+                indexes.len(),
+                probe
+            );
+
+            // The probe already has the flags of the regressed toolchain, and the crates were
+            // already filtered by the blacklist when the parent experiment was created
+            t.execute(
+                "INSERT INTO experiments \
+                 (name, mode, cap_lints, toolchains, priority, created_at, status, \
+                 ignore_blacklist, requirement) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+                &[
+                    &name,
+                    &ex.mode.to_str(),
+                    &ex.cap_lints.to_str(),
+                    &serde_json::to_string(&[probe.to_string()])?,
+                    &ex.priority,
+                    &Utc::now(),
+                    &Status::Queued.to_str(),
+                    &true,
+                    &ex.requirement,
+                ],
+            )?;
+            t.execute(
+                "INSERT INTO bisection_probes (experiment, parent) VALUES (?1, ?2);",
+                &[&name, &ex.name],
+            )?;
+
+            for i in indexes {
+                let krate = serde_json::to_string(&bisections[i].krate)?;
+                t.execute(
+                    "INSERT INTO experiment_crates (experiment, crate, skipped) \
+                     VALUES (?1, ?2, 0);",
+                    &[&name, &krate],
+                )?;
+                // Bisect the same code that was tested in the experiment
+                t.execute(
+                    "INSERT INTO shas (experiment, crate, sha) \
+                     SELECT ?1, crate, sha FROM shas WHERE experiment = ?2 AND crate = ?3;",
+                    &[&name, &ex.name, &krate],
+                )?;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok(true)
+}
+
+/// Record the results of a finished probe experiment in the bisections of its parent. Crates
+/// without a result, for example because the probe failed, can't be bisected any further.
+pub fn record_probe_results(
+    db: &Database,
+    config: &Config,
+    probe: &Experiment,
+    parent: &Experiment,
+) -> Fallible<()> {
+    let results = DatabaseDB::new(db);
+    let toolchain = &probe.toolchains[0];
+    let crates = probe.get_crates(db)?;
+
+    for mut bisection in Bisection::load_all(db, parent)? {
+        if bisection.status != BisectionStatus::Queued || !crates.contains(&bisection.krate) {
+            continue;
+        }
+
+        match results.load_test_result(probe, toolchain, &bisection.krate)? {
+            Some(result) => bisection.record_probe(config, toolchain.clone(), result),
+            None => bisection.status = BisectionStatus::Failed,
+        }
+        bisection.save(db, parent)?;
+    }
+
+    Ok(())
+}
+
+/// Check whether some probes of the current round of an experiment's bisections didn't finish.
+pub fn has_pending_probes(db: &Database, ex: &Experiment) -> Fallible<bool> {
+    let probes = db.query(
+        "SELECT experiment FROM bisection_probes WHERE parent = ?1;",
+        &[&ex.name],
+        |row| row.get::<_, String>("experiment"),
+    )?;
+
+    for name in probes {
+        if let Some(probe) = Experiment::get(db, &name)? {
+            match probe.status {
+                Status::Queued | Status::Running | Status::NeedsReport => return Ok(true),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        has_pending_probes, nightly_date, probe_parent, queue_probes, record_probe_results,
+        Bisection, BisectionStatus,
+    };
+    use crate::actions::{Action, ActionsCtx, CreateExperiment};
+    use crate::config::Config;
+    use crate::crates::Crate;
+    use crate::db::Database;
+    use crate::experiments::{Experiment, Status};
+    use crate::results::{DatabaseDB, EncodingType, FailureReason, TestResult, WriteResults};
+    use crate::toolchain::{MAIN_TOOLCHAIN, TEST_TOOLCHAIN};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_nightly_date() {
+        assert_eq!(
+            nightly_date(&"nightly-2019-03-14".parse().unwrap()),
+            Some(NaiveDate::from_ymd(2019, 3, 14))
+        );
+        assert_eq!(nightly_date(&"nightly".parse().unwrap()), None);
+        assert_eq!(nightly_date(&MAIN_TOOLCHAIN), None);
+    }
+
+    #[test]
+    fn test_bisection_steps() {
+        let config = Config::default();
+        let krate = Crate::Local("build-pass".into());
+
+        // Only dated nightlies can be bisected
+        assert!(Bisection::new(
+            krate.clone(),
+            TestResult::TestPass,
+            MAIN_TOOLCHAIN.clone(),
+            TEST_TOOLCHAIN.clone(),
+        )
+        .is_none());
+
+        let mut bisection = Bisection::new(
+            krate,
+            TestResult::TestPass,
+            "nightly-2019-03-01".parse().unwrap(),
+            "nightly-2019-03-09".parse().unwrap(),
+        )
+        .unwrap();
+
+        let probe = bisection.next_probe().unwrap();
+        assert_eq!(probe.to_string(), "nightly-2019-03-05");
+        bisection.record_probe(&config, probe, TestResult::TestPass);
+
+        let probe = bisection.next_probe().unwrap();
+        assert_eq!(probe.to_string(), "nightly-2019-03-07");
+        let regressed = TestResult::BuildFail(FailureReason::Unknown);
+        bisection.record_probe(&config, probe, regressed);
+
+        let probe = bisection.next_probe().unwrap();
+        assert_eq!(probe.to_string(), "nightly-2019-03-06");
+        bisection.record_probe(&config, probe, regressed);
+
+        assert!(bisection.next_probe().is_none());
+        assert_eq!(bisection.status, BisectionStatus::Queued);
+        assert_eq!(bisection.last_good.to_string(), "nightly-2019-03-05");
+        assert_eq!(bisection.first_bad.to_string(), "nightly-2019-03-06");
+
+        // Spurious failures stop the bisection
+        let probe = "nightly-2019-03-02".parse().unwrap();
+        let spurious = TestResult::BuildFail(FailureReason::OOM);
+        bisection.record_probe(&config, probe, spurious);
+        assert_eq!(bisection.status, BisectionStatus::Failed);
+    }
+
+    #[test]
+    fn test_bisection_probes() {
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        let mut create = CreateExperiment::dummy("foo");
+        create.toolchains = vec![
+            "nightly-2019-03-01".parse().unwrap(),
+            "nightly-2019-03-03".parse().unwrap(),
+        ];
+        create.apply(&ctx).unwrap();
+        let ex = Experiment::get(&db, "foo").unwrap().unwrap();
+        // The name of the first probe is already taken by another experiment
+        CreateExperiment::dummy("foo-bisect-1").apply(&ctx).unwrap();
+
+        let krate = Crate::Local("build-pass".into());
+        Bisection::new(
+            krate.clone(),
+            TestResult::TestPass,
+            ex.toolchains[0].clone(),
+            ex.toolchains[1].clone(),
+        )
+        .unwrap()
+        .save(&db, &ex)
+        .unwrap();
+
+        assert!(queue_probes(&db, &ex).unwrap());
+        let mut probe = Experiment::get(&db, "foo-bisect-2").unwrap().unwrap();
+        assert_eq!(probe.toolchains[0].to_string(), "nightly-2019-03-02");
+        assert_eq!(probe.get_crates(&db).unwrap(), vec![krate.clone()]);
+        assert_eq!(probe_parent(&db, &probe).unwrap().unwrap().name, "foo");
+        assert!(probe_parent(&db, &ex).unwrap().is_none());
+        assert!(has_pending_probes(&db, &ex).unwrap());
+
+        let regressed = TestResult::BuildFail(FailureReason::Unknown);
+        DatabaseDB::new(&db)
+            .record_result(
+                &probe,
+                &probe.toolchains[0],
+                &krate,
+                None,
+                &config,
+                EncodingType::Plain,
+                || Ok(regressed),
+            )
+            .unwrap();
+        record_probe_results(&db, &config, &probe, &ex).unwrap();
+        probe.set_status(&db, Status::Completed).unwrap();
+        assert!(!has_pending_probes(&db, &ex).unwrap());
+
+        // The nightlies are now consecutive, so the bisection is done
+        assert!(!queue_probes(&db, &ex).unwrap());
+        let bisections = Bisection::load_all(&db, &ex).unwrap();
+        assert_eq!(bisections[0].status, BisectionStatus::Completed);
+        assert_eq!(bisections[0].last_good.to_string(), "nightly-2019-03-01");
+        assert_eq!(bisections[0].first_bad.to_string(), "nightly-2019-03-02");
+    }
+}
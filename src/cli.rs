@@ -11,8 +11,9 @@
 
 use crater::actions::{self, Action, ActionsCtx};
 use crater::agent::{self, Capabilities};
+use crater::bisect::{self, Bisection, BisectionStatus};
 use crater::config::Config;
-use crater::crates::Crate;
+use crater::crates::{Crate, DependencyGraph, ListSnapshot};
 use crater::db::Database;
 use crater::experiments::{Assignee, CapLints, CrateSelect, Experiment, Mode, Status};
 use crater::report;
@@ -20,7 +21,8 @@ use crater::results::{DatabaseDB, DeleteResults};
 use crater::runner;
 use crater::server;
 use crater::toolchain::Toolchain;
-use failure::{bail, Error, Fallible};
+use failure::{bail, Error, Fallible, ResultExt};
+use log::info;
 use rustwide::{cmd::SandboxImage, Workspace, WorkspaceBuilder};
 use std::collections::HashSet;
 use std::path::PathBuf;
@@ -74,6 +76,28 @@ fn default_capabilities_for_target() -> Capabilities {
     Capabilities::new(caps)
 }
 
+/// On the CLI `list:@PATH` loads the list of crates from a file, containing one crate per line.
+fn load_crates_list(select: &CrateSelect) -> Fallible<CrateSelect> {
+    if let CrateSelect::List(ref specs) = select {
+        if let [spec] = specs.as_slice() {
+            if spec.starts_with('@') {
+                let path = &spec[1..];
+                let content = std::fs::read_to_string(path)
+                    .with_context(|_| format!("failed to read the list of crates from {}", path))?;
+                let specs = content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.to_string())
+                    .collect();
+                return Ok(CrateSelect::List(specs));
+            }
+        }
+    }
+
+    Ok(select.clone())
+}
+
 #[derive(structopt_derive::StructOpt)]
 #[allow(clippy::large_enum_variant)]
 #[structopt(
@@ -98,14 +122,57 @@ pub enum Crater {
         lists: Vec<String>,
     },
 
+    #[structopt(
+        name = "load-downloads",
+        about = "load the crates.io download counts from a database dump"
+    )]
+    LoadDownloads {
+        #[structopt(name = "path", parse(from_os_str))]
+        path: PathBuf,
+    },
+
+    #[structopt(
+        name = "list-snapshots",
+        about = "show the snapshots of the lists of crates"
+    )]
+    ListSnapshots {
+        #[structopt(name = "list", long = "list")]
+        list: Option<String>,
+        #[structopt(name = "experiment", long = "ex")]
+        ex: Option<String>,
+    },
+
+    #[structopt(
+        name = "prune-lists",
+        about = "delete the old snapshots of the lists not used by any experiment"
+    )]
+    PruneLists {
+        #[structopt(
+            name = "keep",
+            long = "keep",
+            default_value = "1",
+            help = "how many of the latest snapshots of each list to keep"
+        )]
+        keep: usize,
+    },
+
+    #[structopt(
+        name = "diff-lists",
+        about = "compare two snapshots of a list of crates"
+    )]
+    DiffLists {
+        #[structopt(name = "old")]
+        old: i64,
+        #[structopt(name = "new")]
+        new: i64,
+    },
+
     #[structopt(name = "define-ex", about = "define an experiment")]
     DefineEx {
         #[structopt(name = "experiment", long = "ex", default_value = "default")]
         ex: Ex,
-        #[structopt(name = "tc-1")]
-        tc1: Toolchain,
-        #[structopt(name = "tc-2")]
-        tc2: Toolchain,
+        #[structopt(name = "toolchains", raw(required = "true"))]
+        toolchains: Vec<Toolchain>,
         #[structopt(
             name = "mode",
             long = "mode",
@@ -118,10 +185,8 @@ pub enum Crater {
         #[structopt(
             name = "crate-select",
             long = "crate-select",
-            raw(
-                default_value = "CrateSelect::Demo.to_str()",
-                possible_values = "CrateSelect::possible_values()"
-            )
+            default_value = "demo",
+            help = "full, demo, small-random, top-100, local, regressed:EXPERIMENT, list:CRATES or list:@FILE"
         )]
         crates: CrateSelect,
         #[structopt(
@@ -137,6 +202,19 @@ pub enum Crater {
         priority: i32,
         #[structopt(name = "ignore-blacklist", long = "ignore-blacklist")]
         ignore_blacklist: bool,
+        #[structopt(
+            name = "feature-matrix",
+            long = "feature-matrix",
+            help = "also build with --all-features and --no-default-features"
+        )]
+        feature_matrix: bool,
+        #[structopt(
+            name = "targets",
+            long = "targets",
+            raw(use_delimiter = "true"),
+            help = "also check the crates for these targets"
+        )]
+        targets: Vec<String>,
         #[structopt(name = "assign", long = "assign")]
         assign: Option<Assignee>,
         #[structopt(name = "requirement", long = "requirement")]
@@ -151,6 +229,8 @@ pub enum Crater {
         tc1: Option<Toolchain>,
         #[structopt(name = "toolchain-end", long = "end")]
         tc2: Option<Toolchain>,
+        #[structopt(name = "toolchains", long = "toolchains", raw(use_delimiter = "true"))]
+        toolchains: Vec<Toolchain>,
         #[structopt(
             name = "mode",
             long = "mode",
@@ -160,7 +240,7 @@ pub enum Crater {
         #[structopt(
             name = "crates",
             long = "crates",
-            raw(possible_values = "CrateSelect::possible_values()")
+            help = "full, demo, small-random, top-100, local, regressed:EXPERIMENT, list:CRATES or list:@FILE"
         )]
         crates: Option<CrateSelect>,
         #[structopt(
@@ -183,6 +263,20 @@ pub enum Crater {
             conflicts_with = "ignore-blacklist"
         )]
         no_ignore_blacklist: bool,
+        #[structopt(
+            name = "feature-matrix",
+            long = "feature-matrix",
+            conflicts_with = "no-feature-matrix"
+        )]
+        feature_matrix: bool,
+        #[structopt(
+            name = "no-feature-matrix",
+            long = "no-feature-matrix",
+            conflicts_with = "feature-matrix"
+        )]
+        no_feature_matrix: bool,
+        #[structopt(name = "targets", long = "targets", raw(use_delimiter = "true"))]
+        targets: Vec<String>,
         #[structopt(name = "assign", long = "assign")]
         assign: Option<Assignee>,
         #[structopt(name = "requirement", long = "requirement")]
@@ -229,6 +323,21 @@ pub enum Crater {
         fast_workspace_init: bool,
     },
 
+    #[structopt(
+        name = "bisect",
+        about = "bisect the nightlies of the regressions found by an experiment"
+    )]
+    Bisect {
+        #[structopt(name = "experiment", long = "ex", default_value = "default")]
+        ex: Ex,
+        #[structopt(name = "threads", short = "t", long = "threads", default_value = "1")]
+        threads: usize,
+        #[structopt(name = "docker-env", long = "docker-env")]
+        docker_env: Option<String>,
+        #[structopt(name = "fast-workspace-init", long = "fast-workspace-init")]
+        fast_workspace_init: bool,
+    },
+
     #[structopt(name = "gen-report", about = "generate the experiment report")]
     GenReport {
         #[structopt(name = "experiment", long = "ex", default_value = "default")]
@@ -325,6 +434,7 @@ impl Crater {
                         github: lists.remove("github"),
                         registry: lists.remove("registry"),
                         local: lists.remove("local"),
+                        alt_registries: lists.remove("alt-registries"),
                     }
                 };
 
@@ -334,6 +444,74 @@ impl Crater {
                     action.apply(&ctx)?;
                 }
             }
+            Crater::LoadDownloads { ref path } => {
+                let config = Config::load()?;
+                let db = Database::open()?;
+                let ctx = ActionsCtx::new(&db, &config);
+                actions::LoadDownloads { path: path.clone() }.apply(&ctx)?;
+            }
+            Crater::ListSnapshots { ref list, ref ex } => {
+                let db = Database::open()?;
+
+                let snapshots = if let Some(ex) = ex {
+                    if !Experiment::exists(&db, ex)? {
+                        bail!("missing experiment {}", ex);
+                    }
+                    ListSnapshot::of_experiment(&db, ex)?
+                        .into_iter()
+                        .filter(|s| list.is_none() || list.as_ref() == Some(&s.list))
+                        .collect()
+                } else {
+                    ListSnapshot::all(&db, list.as_ref().map(|l| l.as_str()))?
+                };
+
+                for snapshot in &snapshots {
+                    info!(
+                        "snapshot {} of the {} list, loaded at {}",
+                        snapshot.id, snapshot.list, snapshot.loaded_at
+                    );
+                }
+            }
+            Crater::PruneLists { keep } => {
+                let config = Config::load()?;
+                let db = Database::open()?;
+                let ctx = ActionsCtx::new(&db, &config);
+                actions::PruneLists { keep }.apply(&ctx)?;
+            }
+            Crater::DiffLists { old, new } => {
+                let db = Database::open()?;
+
+                let (old, new) = match (ListSnapshot::get(&db, old)?, ListSnapshot::get(&db, new)?)
+                {
+                    (Some(old), Some(new)) => (old, new),
+                    (None, _) => bail!("missing list snapshot {}", old),
+                    (_, None) => bail!("missing list snapshot {}", new),
+                };
+                if old.list != new.list {
+                    bail!(
+                        "can't compare snapshots of different lists ({} and {})",
+                        old.list,
+                        new.list
+                    );
+                }
+
+                let diff = old.diff(&db, &new)?;
+                for krate in &diff.added {
+                    info!("added: {}", krate);
+                }
+                for krate in &diff.removed {
+                    info!("removed: {}", krate);
+                }
+                for (old_krate, new_krate) in &diff.bumped {
+                    info!("bumped: {} -> {}", old_krate, new_krate);
+                }
+                info!(
+                    "{} crates added, {} removed and {} bumped",
+                    diff.added.len(),
+                    diff.removed.len(),
+                    diff.bumped.len()
+                );
+            }
             Crater::PrepareLocal => {
                 let config = Config::load()?;
                 let db = Database::open()?;
@@ -342,13 +520,14 @@ impl Crater {
             }
             Crater::DefineEx {
                 ref ex,
-                ref tc1,
-                ref tc2,
+                ref toolchains,
                 ref mode,
                 ref crates,
                 ref cap_lints,
                 ref priority,
                 ref ignore_blacklist,
+                ref feature_matrix,
+                ref targets,
                 ref assign,
                 ref requirement,
             } => {
@@ -358,13 +537,15 @@ impl Crater {
 
                 actions::CreateExperiment {
                     name: ex.0.clone(),
-                    toolchains: [tc1.clone(), tc2.clone()],
+                    toolchains: toolchains.clone(),
                     mode: *mode,
-                    crates: *crates,
+                    crates: load_crates_list(crates)?,
                     cap_lints: *cap_lints,
                     priority: *priority,
                     github_issue: None,
                     ignore_blacklist: *ignore_blacklist,
+                    feature_matrix: *feature_matrix,
+                    targets: targets.clone(),
                     assign: assign.clone(),
                     requirement: requirement.clone(),
                 }
@@ -374,12 +555,16 @@ impl Crater {
                 ref name,
                 ref tc1,
                 ref tc2,
+                ref toolchains,
                 ref mode,
                 ref crates,
                 ref cap_lints,
                 ref priority,
                 ref ignore_blacklist,
                 ref no_ignore_blacklist,
+                ref feature_matrix,
+                ref no_feature_matrix,
+                ref targets,
                 ref assign,
                 ref requirement,
             } => {
@@ -394,15 +579,34 @@ impl Crater {
                 } else {
                     None
                 };
+                let feature_matrix = if *feature_matrix {
+                    Some(true)
+                } else if *no_feature_matrix {
+                    Some(false)
+                } else {
+                    None
+                };
 
                 actions::EditExperiment {
                     name: name.clone(),
-                    toolchains: [tc1.clone(), tc2.clone()],
+                    toolchains: if toolchains.is_empty() {
+                        None
+                    } else {
+                        Some(toolchains.clone())
+                    },
+                    start: tc1.clone(),
+                    end: tc2.clone(),
                     mode: *mode,
-                    crates: *crates,
+                    crates: crates.as_ref().map(load_crates_list).transpose()?,
                     cap_lints: *cap_lints,
                     priority: *priority,
                     ignore_blacklist,
+                    feature_matrix,
+                    targets: if targets.is_empty() {
+                        None
+                    } else {
+                        Some(targets.clone())
+                    },
                     assign: assign.clone(),
                     requirement: requirement.clone(),
                 }
@@ -489,6 +693,45 @@ impl Crater {
                     bail!("missing experiment {}", ex.0);
                 }
             }
+            Crater::Bisect {
+                ref ex,
+                threads,
+                ref docker_env,
+                fast_workspace_init,
+            } => {
+                let config = Config::load()?;
+                let db = Database::open()?;
+
+                let experiment = match Experiment::get(&db, &ex.0)? {
+                    Some(experiment) => experiment,
+                    None => bail!("missing experiment {}", ex.0),
+                };
+
+                // Queue the bisections if nobody did it yet
+                if Bisection::load_all(&db, &experiment)?.is_empty() {
+                    let ctx = ActionsCtx::new(&db, &config);
+                    actions::CreateBisection { name: ex.0.clone() }.apply(&ctx)?;
+                }
+
+                let bisections = bisect::run_bisections(
+                    &experiment,
+                    &self
+                        .workspace(docker_env.as_ref().map(|s| s.as_str()), fast_workspace_init)?,
+                    &db,
+                    threads,
+                    &config,
+                )?;
+
+                for bisection in &bisections {
+                    match bisection.status {
+                        BisectionStatus::Completed => info!(
+                            "{} regressed between {} and {}",
+                            bisection.krate, bisection.last_good, bisection.first_bad
+                        ),
+                        _ => info!("failed to bisect {}", bisection.krate),
+                    }
+                }
+            }
             Crater::GenReport {
                 ref ex,
                 ref dest,
@@ -517,6 +760,7 @@ impl Crater {
                         &experiment.get_crates(&db)?,
                         &report::FileWriter::create(dest.0.clone())?,
                         &config,
+                        &DependencyGraph::load(&db)?,
                     );
 
                     if let Err(err) = res {
@@ -559,6 +803,7 @@ impl Crater {
                         &experiment.get_crates(&db)?,
                         &report::S3Writer::create(client, s3_prefix.clone())?,
                         &config,
+                        &DependencyGraph::load(&db)?,
                     );
 
                     if let Err(err) = res {
@@ -39,6 +39,17 @@ fn default_false() -> bool {
     false
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RegistryConfig {
+    /// Path or git URL of the registry index.
+    pub index: String,
+    /// URL the crates are downloaded from, like the `dl` key of Cargo's registry configuration.
+    pub download_url: String,
+    #[serde(default)]
+    pub crates: HashMap<String, CrateConfig>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ServerConfig {
@@ -70,12 +81,38 @@ pub struct DemoCrates {
     pub local_crates: Vec<String>,
 }
 
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListsConfig {
+    /// Local copy of the list of GitHub repositories, used instead of downloading it.
+    pub github_csv: Option<PathBuf>,
+    /// Local snapshot of the crates.io index, used instead of cloning it.
+    pub registry_index: Option<PathBuf>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct SandboxConfig {
     pub memory_limit: Size,
     pub build_log_max_size: Size,
     pub build_log_max_lines: usize,
+    #[serde(default)]
+    pub spurious_retries: u32,
+    /// How long the tests of a crate can run under Miri, in seconds.
+    #[serde(default = "default_miri_timeout")]
+    pub miri_timeout: u64,
+    /// How many times the crates are built from scratch, to measure the noise of their compile
+    /// times.
+    #[serde(default = "default_build_samples")]
+    pub build_samples: u32,
+}
+
+fn default_miri_timeout() -> u64 {
+    30 * 60
+}
+
+fn default_build_samples() -> u32 {
+    1
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -85,6 +122,12 @@ pub struct Config {
     pub crates: HashMap<String, CrateConfig>,
     pub github_repos: HashMap<String, CrateConfig>,
     pub local_crates: HashMap<String, CrateConfig>,
+    #[serde(default)]
+    pub git_repos: HashMap<String, CrateConfig>,
+    #[serde(default)]
+    pub registries: HashMap<String, RegistryConfig>,
+    #[serde(default)]
+    pub lists: ListsConfig,
     pub server: ServerConfig,
     pub sandbox: SandboxConfig,
 }
@@ -108,6 +151,11 @@ impl Config {
             Crate::Registry(ref details) => self.crates.get(&details.name),
             Crate::GitHub(ref repo) => self.github_repos.get(&repo.slug()),
             Crate::Local(ref name) => self.local_crates.get(name),
+            Crate::Git(ref repo) => self.git_repos.get(&repo.url),
+            Crate::AltRegistry(ref details) => self
+                .registries
+                .get(&details.registry)
+                .and_then(|registry| registry.crates.get(&details.name)),
         }
     }
 
@@ -242,10 +290,16 @@ impl Default for Config {
             crates: HashMap::new(),
             github_repos: HashMap::new(),
             local_crates: HashMap::new(),
+            git_repos: HashMap::new(),
+            registries: HashMap::new(),
+            lists: ListsConfig::default(),
             sandbox: SandboxConfig {
                 memory_limit: Size::Gigabytes(2),
                 build_log_max_size: Size::Megabytes(1),
                 build_log_max_lines: 1000,
+                spurious_retries: 0,
+                miri_timeout: default_miri_timeout(),
+                build_samples: default_build_samples(),
             },
             server: ServerConfig {
                 bot_acl: BotACL {
@@ -274,7 +328,7 @@ impl From<&'_ Config> for LogStorage {
 #[cfg(test)]
 mod tests {
     use super::Config;
-    use crate::crates::{Crate, GitHubRepo, RegistryCrate};
+    use crate::crates::{AltRegistryCrate, Crate, GitHubRepo, GitRepo, RegistryCrate};
 
     #[test]
     fn test_config() {
@@ -299,7 +353,14 @@ mod tests {
             "lazy_static = { skip = true }\n",
             "[github-repos]\n",
             "\"rust-lang/rust\" = { quiet = true }\n", // :(
-            "[local-crates]\n"
+            "[local-crates]\n",
+            "[git-repos]\n",
+            "\"https://gitlab.com/foo/bar\" = { broken = true }\n",
+            "[registries.internal]\n",
+            "index = \"https://git.example.com/index\"\n",
+            "download-url = \"https://dl.example.com\"\n",
+            "[registries.internal.crates]\n",
+            "lazy_static = { skip-tests = true }\n"
         );
 
         let list: Config = ::toml::from_str(&config).unwrap();
@@ -321,5 +382,22 @@ mod tests {
             org: "rust-lang".into(),
             name: "cargo".into(),
         })));
+
+        assert!(list.is_broken(&Crate::Git(GitRepo {
+            url: "https://gitlab.com/foo/bar".into(),
+            rev: None,
+        })));
+
+        let alt = |registry: &str| {
+            Crate::AltRegistry(AltRegistryCrate {
+                registry: registry.into(),
+                name: "lazy_static".into(),
+                version: "42".into(),
+                checksum: String::new(),
+            })
+        };
+        assert!(list.should_skip_tests(&alt("internal")));
+        assert!(!list.should_skip(&alt("internal")));
+        assert!(!list.should_skip_tests(&alt("other")));
     }
 }
@@ -0,0 +1,95 @@
+use crate::db::{Database, QueryUtils};
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Dependency graph of the registry crates, built from the index when the lists are updated.
+#[derive(Default)]
+pub struct DependencyGraph {
+    dependencies: HashMap<String, HashSet<String>>,
+    dependents: HashMap<String, HashSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn load(db: &Database) -> Fallible<Self> {
+        let edges = db.query(
+            "SELECT crate, dependency FROM registry_dependencies;",
+            &[],
+            |row| -> (String, String) { (row.get("crate"), row.get("dependency")) },
+        )?;
+
+        Ok(edges.into_iter().collect())
+    }
+
+    /// Check whether `krate` depends on `dependency`, either directly or transitively.
+    pub fn depends_on(&self, krate: &str, dependency: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut queue = vec![krate];
+        while let Some(current) = queue.pop() {
+            if let Some(deps) = self.dependencies.get(current) {
+                if deps.contains(dependency) {
+                    return true;
+                }
+                queue.extend(
+                    deps.iter()
+                        .map(|d| d.as_str())
+                        .filter(|d| visited.insert(*d)),
+                );
+            }
+        }
+
+        false
+    }
+
+    pub fn contains(&self, krate: &str) -> bool {
+        self.dependencies.contains_key(krate) || self.dependents.contains_key(krate)
+    }
+
+    /// The number of registry crates directly depending on `krate`.
+    pub fn dependents_count(&self, krate: &str) -> usize {
+        self.dependents.get(krate).map(|d| d.len()).unwrap_or(0)
+    }
+}
+
+impl std::iter::FromIterator<(String, String)> for DependencyGraph {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(edges: I) -> Self {
+        let mut graph = DependencyGraph::default();
+        for (krate, dependency) in edges {
+            graph
+                .dependents
+                .entry(dependency.clone())
+                .or_insert_with(HashSet::new)
+                .insert(krate.clone());
+            graph
+                .dependencies
+                .entry(krate)
+                .or_insert_with(HashSet::new)
+                .insert(dependency);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DependencyGraph;
+
+    #[test]
+    fn test_dependency_graph() {
+        let graph = vec![("a", "b"), ("b", "c"), ("d", "c"), ("c", "a")]
+            .into_iter()
+            .map(|(krate, dep)| (krate.to_string(), dep.to_string()))
+            .collect::<DependencyGraph>();
+
+        assert!(graph.depends_on("a", "b"));
+        assert!(graph.depends_on("a", "c"));
+        assert!(graph.depends_on("d", "b"));
+        assert!(!graph.depends_on("a", "d"));
+        assert!(!graph.depends_on("e", "a"));
+
+        assert!(graph.contains("d"));
+        assert!(!graph.contains("e"));
+
+        assert_eq!(graph.dependents_count("c"), 2);
+        assert_eq!(graph.dependents_count("d"), 0);
+    }
+}
@@ -1,18 +1,23 @@
 use crate::config::Config;
 use crate::crates::{Crate, RegistryCrate};
 use crate::db::{Database, QueryUtils};
-use crate::experiments::CrateSelect;
+use crate::experiments::{CrateSelect, Experiment, Status};
 use crate::prelude::*;
-use chrono::Utc;
-use rand::{thread_rng, Rng};
-use std::collections::HashSet;
+use crate::report;
+use crate::results::DatabaseDB;
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+use rusqlite::Row;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 
 pub(crate) use crate::crates::sources::{
-    github::GitHubList, local::LocalList, registry::RegistryList,
+    alt_registry::AltRegistryList,
+    github::GitHubList,
+    local::LocalList,
+    registry::{RegistryList, RegistryVersionsList},
 };
 
-const SMALL_RANDOM_COUNT: usize = 20;
-
 pub(crate) trait List {
     const NAME: &'static str;
 
@@ -21,14 +26,19 @@ pub(crate) trait List {
     fn update(&self, db: &Database) -> Fallible<()> {
         let crates = self.fetch()?;
 
+        // Every update creates a new snapshot of the list, keeping the previous ones around
         let now = Utc::now();
-        db.transaction(|t| {
-            // Replace the existing list in the database
-            t.execute("DELETE FROM crates WHERE list = ?1;", &[&Self::NAME])?;
+        let snapshot = db.transaction(|t| {
+            t.execute(
+                "INSERT INTO list_snapshots (list, loaded_at) VALUES (?1, ?2);",
+                &[&Self::NAME, &now],
+            )?;
+            let snapshot = t.with_conn(|conn| Ok(conn.last_insert_rowid()))?;
+
             for krate in &crates {
-                t.execute(
-                    "INSERT INTO crates (crate, list, loaded_at) VALUES (?1, ?2, ?3);",
-                    &[&::serde_json::to_string(krate)?, &Self::NAME, &now],
+                t.execute_cached(
+                    "INSERT INTO crates (crate, snapshot) VALUES (?1, ?2);",
+                    &[&::serde_json::to_string(krate)?, &snapshot],
                 )
                 .with_context(|_| {
                     format!(
@@ -39,17 +49,104 @@ pub(crate) trait List {
                 })?;
             }
 
-            Ok(())
+            Ok(snapshot)
         })?;
 
-        info!("loaded {} crates in the {} list", crates.len(), Self::NAME);
+        info!(
+            "loaded {} crates in the {} list (snapshot {})",
+            crates.len(),
+            Self::NAME,
+            snapshot
+        );
         Ok(())
     }
 
+    /// The crates in the latest snapshot of the list.
     fn get(db: &Database) -> Fallible<Vec<Crate>> {
+        match ListSnapshot::latest(db, Self::NAME)? {
+            Some(snapshot) => snapshot.crates(db),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// A version of a list, recorded every time the list is updated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListSnapshot {
+    pub id: i64,
+    pub list: String,
+    pub loaded_at: DateTime<Utc>,
+}
+
+/// The differences between two snapshots of a list.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    pub added: Vec<Crate>,
+    pub removed: Vec<Crate>,
+    /// Crates that changed version between the snapshots, as `(old, new)` pairs.
+    pub bumped: Vec<(Crate, Crate)>,
+}
+
+impl ListSnapshot {
+    fn from_row(row: &Row) -> Self {
+        ListSnapshot {
+            id: row.get("id"),
+            list: row.get("list"),
+            loaded_at: row.get("loaded_at"),
+        }
+    }
+
+    pub fn get(db: &Database, id: i64) -> Fallible<Option<ListSnapshot>> {
+        Ok(db
+            .query(
+                "SELECT * FROM list_snapshots WHERE id = ?1;",
+                &[&id],
+                ListSnapshot::from_row,
+            )?
+            .pop())
+    }
+
+    pub fn latest(db: &Database, list: &str) -> Fallible<Option<ListSnapshot>> {
+        Ok(db
+            .query(
+                "SELECT * FROM list_snapshots WHERE list = ?1 ORDER BY id DESC LIMIT 1;",
+                &[&list],
+                ListSnapshot::from_row,
+            )?
+            .pop())
+    }
+
+    /// All the snapshots, optionally only of a list, from the oldest to the newest.
+    pub fn all(db: &Database, list: Option<&str>) -> Fallible<Vec<ListSnapshot>> {
+        match list {
+            Some(list) => db.query(
+                "SELECT * FROM list_snapshots WHERE list = ?1 ORDER BY id;",
+                &[&list],
+                ListSnapshot::from_row,
+            ),
+            None => db.query(
+                "SELECT * FROM list_snapshots ORDER BY id;",
+                &[],
+                ListSnapshot::from_row,
+            ),
+        }
+    }
+
+    /// The snapshots the crates of an experiment were selected from.
+    pub fn of_experiment(db: &Database, ex: &str) -> Fallible<Vec<ListSnapshot>> {
+        db.query(
+            "SELECT list_snapshots.* FROM list_snapshots \
+             INNER JOIN experiment_list_snapshots ON experiment_list_snapshots.snapshot = id \
+             WHERE experiment_list_snapshots.experiment = ?1 ORDER BY id;",
+            &[&ex],
+            ListSnapshot::from_row,
+        )
+    }
+
+    pub fn crates(&self, db: &Database) -> Fallible<Vec<Crate>> {
         let crates_results = db.query(
-            "SELECT crate FROM crates WHERE list = ?1 ORDER BY rowid;",
-            &[&Self::NAME],
+            "SELECT crate FROM crates WHERE snapshot = ?1 ORDER BY rowid;",
+            &[&self.id],
             |r| {
                 let raw: String = r.get("crate");
                 Ok(::serde_json::from_str(&raw)?)
@@ -59,6 +156,129 @@ pub(crate) trait List {
         // Turns Vec<Fallible<Crate>> into Fallible<Vec<Crate>>
         crates_results.into_iter().collect()
     }
+
+    /// Compare this snapshot with a newer one.
+    pub fn diff(&self, db: &Database, newer: &ListSnapshot) -> Fallible<SnapshotDiff> {
+        let old = self.crates(db)?.into_iter().collect::<HashSet<_>>();
+        let new = newer.crates(db)?.into_iter().collect::<HashSet<_>>();
+
+        let mut removed = old.difference(&new).cloned().collect::<Vec<_>>();
+        let mut added = new.difference(&old).cloned().collect::<Vec<_>>();
+        removed.sort();
+        added.sort();
+
+        // A crate was bumped if it's the only one with its name that was both removed and
+        // added. Lists with multiple versions of the same crate can't be paired otherwise
+        let mut by_name = HashMap::new();
+        for krate in removed.iter().chain(added.iter()) {
+            if let Some(name) = versioned_name(krate) {
+                *by_name.entry(name).or_insert(0) += 1;
+            }
+        }
+        let is_bump = |krate: &Crate| versioned_name(krate).map(|n| by_name[&n]) == Some(2);
+
+        let mut diff = SnapshotDiff::default();
+        let mut new_versions = HashMap::new();
+        for krate in added {
+            if is_bump(&krate) {
+                new_versions.insert(versioned_name(&krate).unwrap(), krate);
+            } else {
+                diff.added.push(krate);
+            }
+        }
+        for krate in removed {
+            match versioned_name(&krate).and_then(|name| new_versions.remove(&name)) {
+                Some(new) => diff.bumped.push((krate, new)),
+                None => diff.removed.push(krate),
+            }
+        }
+        diff.added
+            .extend(new_versions.into_iter().map(|(_, krate)| krate));
+        diff.added.sort();
+
+        Ok(diff)
+    }
+}
+
+/// The name of a crate that can appear with a different version in another snapshot.
+fn versioned_name(krate: &Crate) -> Option<String> {
+    match krate {
+        Crate::Registry(RegistryCrate { name, .. }) => Some(format!("registry/{}", name)),
+        Crate::AltRegistry(details) => Some(format!("alt/{}/{}", details.registry, details.name)),
+        Crate::Git(repo) => Some(format!("git/{}", repo.url)),
+        Crate::GitHub(_) | Crate::Local(_) => None,
+    }
+}
+
+/// The lists the crates of a selection are picked from.
+fn source_lists(select: &CrateSelect) -> &'static [&'static str] {
+    match select {
+        CrateSelect::Full | CrateSelect::Random { .. } => &[RegistryList::NAME, GitHubList::NAME],
+        CrateSelect::Demo | CrateSelect::List(_) => {
+            &[RegistryList::NAME, GitHubList::NAME, LocalList::NAME]
+        }
+        CrateSelect::Top(_) | CrateSelect::Percent(_) => &[RegistryList::NAME],
+        CrateSelect::Local => &[LocalList::NAME],
+        CrateSelect::AltRegistry(_) => &[AltRegistryList::NAME],
+        CrateSelect::AllVersions => &[RegistryVersionsList::NAME],
+        CrateSelect::Versions(_) | CrateSelect::Regressed(_) => &[],
+    }
+}
+
+/// The snapshots `get_crates` picks the crates of a selection from.
+pub(crate) fn latest_snapshots(select: &CrateSelect, db: &Database) -> Fallible<Vec<ListSnapshot>> {
+    let mut snapshots = Vec::new();
+    for list in source_lists(select) {
+        if let Some(snapshot) = ListSnapshot::latest(db, list)? {
+            snapshots.push(snapshot);
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// The crates.io crates sorted by popularity. The download counts are used if they were
+/// loaded, otherwise the crates are sorted by their number of reverse dependencies.
+fn popular_registry_crates(db: &Database) -> Fallible<Vec<Crate>> {
+    let mut crates = RegistryList::get(db)?;
+
+    let downloads = db
+        .query(
+            "SELECT crate, downloads FROM crate_downloads;",
+            &[],
+            |row| -> (String, i64) { (row.get("crate"), row.get("downloads")) },
+        )?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    if !downloads.is_empty() {
+        // The sort is stable, so crates without downloads keep their previous order
+        crates.sort_by_key(|krate| match krate {
+            Crate::Registry(RegistryCrate { name, .. }) => {
+                Reverse(downloads.get(name).cloned().unwrap_or(0))
+            }
+            _ => Reverse(0),
+        });
+    }
+
+    Ok(crates)
+}
+
+/// The commits GitHub repos are pinned to in an explicit list of crates, with the
+/// `https://github.com/org/repo#sha` syntax.
+pub(crate) fn pinned_shas(select: &CrateSelect) -> Fallible<Vec<(Crate, String)>> {
+    let mut shas = Vec::new();
+    if let CrateSelect::List(specs) = select {
+        for spec in specs {
+            let mut parts = spec.splitn(2, '#');
+            if let (Some(url), Some(sha)) = (parts.next(), parts.next()) {
+                if url.starts_with("https://github.com/") && !sha.is_empty() {
+                    shas.push((Crate::GitHub(url.parse()?), sha.to_string()));
+                }
+            }
+        }
+    }
+
+    Ok(shas)
 }
 
 pub(crate) fn get_crates(
@@ -96,6 +316,7 @@ pub(crate) fn get_crates(
                     Crate::Registry(RegistryCrate { ref name, .. }) => demo_registry.remove(name),
                     Crate::GitHub(ref repo) => demo_github.remove(&repo.slug()),
                     Crate::Local(ref name) => demo_local.remove(name),
+                    Crate::AltRegistry(_) | Crate::Git(_) => false,
                 };
 
                 if add {
@@ -114,21 +335,92 @@ pub(crate) fn get_crates(
                 bail!("missing demo local crates: {:?}", demo_local);
             }
         }
-        CrateSelect::SmallRandom => {
+        CrateSelect::Random { count, seed } => {
             crates.append(&mut RegistryList::get(db)?);
             crates.append(&mut GitHubList::get(db)?);
 
-            let mut rng = thread_rng();
+            let seed = seed.unwrap_or_else(|| thread_rng().gen());
+            let mut seed_bytes = <StdRng as SeedableRng>::Seed::default();
+            seed_bytes[..4].copy_from_slice(&seed.to_le_bytes());
+
+            let mut rng = StdRng::from_seed(seed_bytes);
             rng.shuffle(&mut crates);
-            crates.truncate(SMALL_RANDOM_COUNT);
+            crates.truncate(count as usize);
         }
-        CrateSelect::Top100 => {
-            crates.append(&mut RegistryList::get(db)?);
-            crates.truncate(100);
+        CrateSelect::Top(count) => {
+            crates.append(&mut popular_registry_crates(db)?);
+            crates.truncate(count as usize);
+        }
+        CrateSelect::Percent(percent) => {
+            crates.append(&mut popular_registry_crates(db)?);
+            let count = crates.len() * percent as usize / 100;
+            crates.truncate(count);
         }
         CrateSelect::Local => {
             crates.append(&mut LocalList::get(db)?);
         }
+        CrateSelect::AltRegistry(name) => {
+            if !config.registries.contains_key(&name) {
+                bail!("registry {} is not configured", name);
+            }
+            crates.extend(
+                AltRegistryList::get(db)?
+                    .into_iter()
+                    .filter(|krate| match krate {
+                        Crate::AltRegistry(details) => details.registry == name,
+                        _ => false,
+                    }),
+            );
+        }
+        CrateSelect::AllVersions => {
+            crates.append(&mut RegistryVersionsList::get(db)?);
+        }
+        CrateSelect::Versions(names) => {
+            crates.append(&mut RegistryList::new(config).all_versions_of(&names)?);
+        }
+        CrateSelect::Regressed(name) => {
+            let ex = match Experiment::get(db, &name)? {
+                Some(ex) => ex,
+                None => bail!("experiment {} not found", name),
+            };
+            if ex.status == Status::Queued || ex.status == Status::Running {
+                bail!("experiment {} didn't finish running yet", name);
+            }
+
+            // This includes both the regressed and spurious regressed crates
+            let regressions =
+                report::find_regressions(&DatabaseDB::new(db), config, &ex, &ex.get_crates(db)?)?;
+            crates.extend(regressions.into_iter().map(|regression| regression.krate));
+        }
+        CrateSelect::List(specs) => {
+            let mut known = HashMap::new();
+            for krate in LocalList::get(db)?
+                .into_iter()
+                .chain(GitHubList::get(db)?)
+                .chain(RegistryList::get(db)?)
+            {
+                let name = match krate {
+                    Crate::Registry(RegistryCrate { ref name, .. }) => name.clone(),
+                    Crate::GitHub(ref repo) => repo.slug(),
+                    Crate::Local(ref name) => name.clone(),
+                    Crate::AltRegistry(_) | Crate::Git(_) => continue,
+                };
+                // Registry crates take precedence over the others with the same name
+                known.insert(name, krate);
+            }
+
+            for spec in &specs {
+                let krate = match known.get(spec.as_str()) {
+                    Some(krate) => krate.clone(),
+                    None => spec
+                        .parse::<Crate>()
+                        .with_context(|_| format!("invalid crate in the list: {}", spec))?,
+                };
+                if !crates.contains(&krate) {
+                    crates.push(krate);
+                }
+            }
+        }
     }
 
     crates.sort();
@@ -143,6 +435,88 @@ pub(crate) fn setup_test_lists(db: &Database, config: &Config) -> Fallible<()> {
         github: false,
         registry: false,
         local: true,
+        alt_registries: false,
     }
     .apply(&ActionsCtx::new(db, config))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{List, ListSnapshot, SnapshotDiff};
+    use crate::crates::{Crate, GitHubRepo, RegistryCrate};
+    use crate::db::Database;
+    use crate::prelude::*;
+
+    struct DummyList(Vec<Crate>);
+
+    impl List for DummyList {
+        const NAME: &'static str = "dummy";
+
+        fn fetch(&self) -> Fallible<Vec<Crate>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn registry(name: &str, version: &str) -> Crate {
+        Crate::Registry(RegistryCrate {
+            name: name.into(),
+            version: version.into(),
+        })
+    }
+
+    fn github(org: &str, name: &str) -> Crate {
+        Crate::GitHub(GitHubRepo {
+            org: org.into(),
+            name: name.into(),
+        })
+    }
+
+    #[test]
+    fn test_snapshots_diff() {
+        let db = Database::temp().unwrap();
+
+        DummyList(vec![
+            registry("lazy_static", "1.0.0"),
+            registry("syn", "0.15.0"),
+            registry("syn", "1.0.0"),
+            registry("removed", "1.0.0"),
+            github("brson", "hello-rs"),
+        ])
+        .update(&db)
+        .unwrap();
+        let old = ListSnapshot::latest(&db, "dummy").unwrap().unwrap();
+
+        let new_crates = vec![
+            registry("lazy_static", "1.4.0"),
+            registry("syn", "0.15.0"),
+            registry("syn", "1.0.1"),
+            registry("added", "0.1.0"),
+            github("rust-lang", "crater"),
+        ];
+        DummyList(new_crates.clone()).update(&db).unwrap();
+        let new = ListSnapshot::latest(&db, "dummy").unwrap().unwrap();
+
+        // Old snapshots are kept, while the list only returns the latest one
+        assert_ne!(old.id, new.id);
+        assert_eq!(
+            ListSnapshot::all(&db, Some("dummy")).unwrap(),
+            vec![old.clone(), new.clone()]
+        );
+        assert_eq!(DummyList::get(&db).unwrap(), new_crates);
+
+        assert_eq!(
+            old.diff(&db, &new).unwrap(),
+            SnapshotDiff {
+                added: vec![registry("added", "0.1.0"), github("rust-lang", "crater")],
+                removed: vec![registry("removed", "1.0.0"), github("brson", "hello-rs")],
+                bumped: vec![
+                    (
+                        registry("lazy_static", "1.0.0"),
+                        registry("lazy_static", "1.4.0")
+                    ),
+                    (registry("syn", "1.0.0"), registry("syn", "1.0.1")),
+                ],
+            }
+        );
+    }
+}
@@ -1,3 +1,4 @@
+mod dependencies;
 pub(crate) mod lists;
 mod sources;
 
@@ -7,6 +8,10 @@ use rustwide::Crate as RustwideCrate;
 use std::fmt;
 use std::str::FromStr;
 
+pub use crate::crates::dependencies::DependencyGraph;
+pub use crate::crates::lists::{ListSnapshot, SnapshotDiff};
+pub(crate) use crate::crates::sources::alt_registry::AltRegistryCrate;
+pub(crate) use crate::crates::sources::git::GitRepo;
 pub(crate) use crate::crates::sources::github::GitHubRepo;
 pub(crate) use crate::crates::sources::registry::RegistryCrate;
 
@@ -15,6 +20,8 @@ pub enum Crate {
     Registry(RegistryCrate),
     GitHub(GitHubRepo),
     Local(String),
+    AltRegistry(AltRegistryCrate),
+    Git(GitRepo),
 }
 
 impl Crate {
@@ -23,16 +30,37 @@ impl Crate {
             Crate::Registry(ref details) => format!("reg/{}/{}", details.name, details.version),
             Crate::GitHub(ref repo) => format!("gh/{}/{}", repo.org, repo.name),
             Crate::Local(ref name) => format!("local/{}", name),
+            Crate::AltRegistry(ref details) => format!(
+                "alt/{}/{}/{}",
+                details.registry, details.name, details.version
+            ),
+            Crate::Git(ref repo) => match repo.rev {
+                Some(ref rev) => format!("git/{}/{}", repo.url, rev),
+                None => format!("git/{}", repo.url),
+            },
+        }
+    }
+
+    /// The git repository of the crate, if it's fetched with git.
+    pub(crate) fn git_repo(&self) -> Option<GitRepo> {
+        match self {
+            Crate::GitHub(repo) => Some(repo.git_repo()),
+            Crate::Git(repo) => Some(repo.clone()),
+            _ => None,
         }
     }
 
     pub(crate) fn to_rustwide(&self) -> RustwideCrate {
         match self {
             Self::Registry(krate) => RustwideCrate::crates_io(&krate.name, &krate.version),
-            Self::GitHub(repo) => {
-                RustwideCrate::git(&format!("https://github.com/{}/{}", repo.org, repo.name))
-            }
+            Self::GitHub(repo) => RustwideCrate::local(&repo.git_repo().checkout_dir()),
             Self::Local(name) => RustwideCrate::local(&LOCAL_CRATES_DIR.join(name)),
+            // The crate is downloaded by Crater before being handed to rustwide, as rustwide
+            // only supports crates.io
+            Self::AltRegistry(krate) => RustwideCrate::local(&krate.source_dir()),
+            // Crater checks out the revision to test itself for git crates, as rustwide only
+            // fetches the default branch of git repositories
+            Self::Git(repo) => RustwideCrate::local(&repo.checkout_dir()),
         }
     }
 }
@@ -46,6 +74,13 @@ impl fmt::Display for Crate {
                 Crate::Registry(ref krate) => format!("{}-{}", krate.name, krate.version),
                 Crate::GitHub(ref repo) => repo.slug(),
                 Crate::Local(ref name) => format!("{} (local)", name),
+                Crate::AltRegistry(ref krate) => {
+                    format!("{}-{} ({})", krate.name, krate.version, krate.registry)
+                }
+                Crate::Git(ref repo) => match repo.rev {
+                    Some(ref rev) => format!("{}#{}", repo.url, rev),
+                    None => repo.url.clone(),
+                },
             }
         )
     }
@@ -56,7 +91,25 @@ impl FromStr for Crate {
 
     fn from_str(s: &str) -> Fallible<Self> {
         if s.starts_with("https://github.com/") {
-            Ok(Crate::GitHub(s.parse()?))
+            // A pinned commit can be appended to the url, see `lists::pinned_shas`
+            let url = s.splitn(2, '#').next().unwrap();
+            Ok(Crate::GitHub(url.parse()?))
+        } else if s.starts_with("git+") {
+            // Same syntax as the git sources in Cargo's package ids
+            let mut parts = s["git+".len()..].splitn(2, '#');
+            let url = parts.next().unwrap();
+            let rev = parts.next();
+            if url.is_empty() {
+                bail!("missing git repository url");
+            }
+            // They would be parsed as options by git
+            if url.starts_with('-') || rev.map(|rev| rev.starts_with('-')).unwrap_or(false) {
+                bail!("invalid git repository: {}", s);
+            }
+            Ok(Crate::Git(GitRepo {
+                url: url.to_string(),
+                rev: rev.map(|rev| rev.to_string()),
+            }))
         } else if let Some(dash_idx) = s.rfind('-') {
             let name = &s[..dash_idx];
             let version = &s[dash_idx + 1..];
@@ -0,0 +1,391 @@
+use crate::config::{Config, RegistryConfig};
+use crate::crates::sources::registry::latest_version;
+use crate::crates::{lists::List, Crate};
+use crate::dirs::WORK_DIR;
+use crate::prelude::*;
+use crate::utils::hex::from_hex;
+use crates_index::Index;
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tar::Archive;
+
+fn registries_dir() -> PathBuf {
+    WORK_DIR.join("alt-registries")
+}
+
+/// The crates published on the alternative registries defined in the configuration.
+pub(crate) struct AltRegistryList {
+    registries: BTreeMap<String, RegistryConfig>,
+}
+
+impl AltRegistryList {
+    pub(crate) fn new(config: &Config) -> Self {
+        AltRegistryList {
+            registries: config
+                .registries
+                .iter()
+                .map(|(name, registry)| (name.clone(), registry.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// The local copy of the index of a registry, either its path or the checkout of its git URL.
+fn index_dir(name: &str, registry: &RegistryConfig) -> PathBuf {
+    if registry.index.contains("://") {
+        registries_dir().join(format!("{}-index", name))
+    } else {
+        registry.index.clone().into()
+    }
+}
+
+/// The file describing every version of a crate in a registry index, following Cargo's layout.
+fn index_file(index: &Path, name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => index.join("1").join(&name),
+        2 => index.join("2").join(&name),
+        3 => index.join("3").join(&name[..1]).join(&name),
+        _ => index.join(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+#[derive(Deserialize)]
+struct IndexVersion {
+    vers: String,
+    cksum: String,
+}
+
+/// The SHA-256 checksum of the tarball of a crate version, as recorded in the index.
+fn index_checksum(index: &Path, name: &str, version: &str) -> Fallible<String> {
+    let path = index_file(index, name);
+    let content =
+        fs::read_to_string(&path).with_context(|_| format!("failed to read {}", path.display()))?;
+
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: IndexVersion = serde_json::from_str(line)?;
+        if entry.vers == version {
+            return Ok(entry.cksum);
+        }
+    }
+
+    bail!("{} {} is missing from the index", name, version);
+}
+
+/// Get a local checkout of the index of a registry, cloning or updating it if it's a git URL.
+fn fetch_index(name: &str, registry: &RegistryConfig) -> Fallible<PathBuf> {
+    let dest = index_dir(name, registry);
+    if !registry.index.contains("://") {
+        return Ok(dest);
+    }
+
+    if dest.join(".git").is_dir() {
+        git(&dest, &["fetch", "--quiet", &registry.index])?;
+        git(&dest, &["reset", "--quiet", "--hard", "FETCH_HEAD"])?;
+    } else {
+        fs::create_dir_all(registries_dir())?;
+        git(
+            &registries_dir(),
+            &["clone", "--quiet", &registry.index, &dest.to_string_lossy()],
+        )?;
+    }
+
+    Ok(dest)
+}
+
+fn git(cwd: &Path, args: &[&str]) -> Fallible<()> {
+    let status = Command::new("git").args(args).current_dir(cwd).status()?;
+    if !status.success() {
+        bail!("failed to run `git {}`", args.join(" "));
+    }
+    Ok(())
+}
+
+impl List for AltRegistryList {
+    const NAME: &'static str = "alt-registries";
+
+    fn fetch(&self) -> Fallible<Vec<Crate>> {
+        let mut list = Vec::new();
+
+        for (name, registry) in &self.registries {
+            info!("loading the index of the {} registry", name);
+            let index_dir = fetch_index(name, registry)
+                .with_context(|_| format!("failed to fetch the index of registry {}", name))?;
+            let index = Index::new(index_dir.clone());
+
+            for krate in index.crates() {
+                if let Some(version) = latest_version(&krate) {
+                    // The agents don't have the index, so the checksum is stored in the list
+                    let checksum = index_checksum(&index_dir, krate.name(), version.version())?;
+                    list.push(Crate::AltRegistry(AltRegistryCrate {
+                        registry: name.clone(),
+                        name: krate.name().to_string(),
+                        version: version.version().to_string(),
+                        checksum,
+                    }));
+                }
+            }
+        }
+
+        Ok(list)
+    }
+}
+
+/// Build the URL of a crate tarball from the download URL of a registry, which supports the same
+/// `{crate}` and `{version}` markers as the `dl` key of Cargo's registry configuration.
+fn download_url(template: &str, name: &str, version: &str) -> String {
+    if template.contains("{crate}") || template.contains("{version}") {
+        template
+            .replace("{crate}", name)
+            .replace("{version}", version)
+    } else {
+        format!(
+            "{}/{}/{}/download",
+            template.trim_end_matches('/'),
+            name,
+            version
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Clone)]
+pub struct AltRegistryCrate {
+    pub registry: String,
+    pub name: String,
+    pub version: String,
+    /// The SHA-256 checksum of the tarball of the crate, as recorded in the index of its
+    /// registry when the list was updated.
+    #[serde(default)]
+    pub checksum: String,
+}
+
+impl AltRegistryCrate {
+    /// The directory the source code of the crate is extracted to.
+    pub(crate) fn source_dir(&self) -> PathBuf {
+        registries_dir()
+            .join(&self.registry)
+            .join(format!("{}-{}", self.name, self.version))
+    }
+
+    fn registry<'a>(&self, config: &'a Config) -> Fallible<&'a RegistryConfig> {
+        match config.registries.get(&self.registry) {
+            Some(registry) => Ok(registry),
+            None => bail!("registry {} is not configured", self.registry),
+        }
+    }
+
+    pub(crate) fn download_url(&self, config: &Config) -> Fallible<String> {
+        let registry = self.registry(config)?;
+        Ok(download_url(
+            &registry.download_url,
+            &self.name,
+            &self.version,
+        ))
+    }
+
+    /// Download and extract the source code of the crate, if it wasn't already.
+    pub(crate) fn fetch(&self, config: &Config) -> Fallible<()> {
+        let dest = self.source_dir();
+        if dest.is_dir() {
+            return Ok(());
+        }
+
+        if self.checksum.is_empty() {
+            bail!(
+                "no checksum was recorded for {}, update the lists",
+                self.name
+            );
+        }
+
+        let url = self.download_url(config)?;
+        info!("downloading {} from {}", self.name, url);
+        let mut tarball = Vec::new();
+        crate::utils::http::get_sync(&url)
+            .with_context(|_| format!("failed to download {}", url))?
+            .read_to_end(&mut tarball)?;
+
+        // The registry could serve anything, so only the tarball recorded in the index is trusted
+        if Sha256::digest(&tarball).as_slice() != from_hex(&self.checksum)?.as_slice() {
+            bail!("the checksum of {} doesn't match the index", url);
+        }
+
+        // Crate tarballs contain a single `name-version` directory. They're extracted in a
+        // temporary directory first, to avoid leaving half-extracted crates around
+        let parent = dest.parent().unwrap();
+        fs::create_dir_all(parent)?;
+        let tmp = tempfile::tempdir_in(parent)?;
+        Archive::new(GzDecoder::new(tarball.as_slice())).unpack(tmp.path())?;
+
+        let extracted = tmp.path().join(dest.file_name().unwrap());
+        if !extracted.is_dir() {
+            bail!("the tarball of {} has an unexpected layout", self.name);
+        }
+        fs::rename(&extracted, &dest)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{download_url, index_file, AltRegistryList};
+    use crate::config::{Config, RegistryConfig};
+    use crate::crates::{lists::List, AltRegistryCrate, Crate};
+    use flate2::{write::GzEncoder, Compression};
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::thread;
+
+    #[test]
+    fn test_download_url() {
+        assert_eq!(
+            download_url("https://dl.example.com/api/v1/crates", "foo", "1.0.0"),
+            "https://dl.example.com/api/v1/crates/foo/1.0.0/download"
+        );
+        assert_eq!(
+            download_url(
+                "https://dl.example.com/{crate}-{version}.crate",
+                "foo",
+                "1.0.0"
+            ),
+            "https://dl.example.com/foo-1.0.0.crate"
+        );
+    }
+
+    #[test]
+    fn test_index_file() {
+        let index = Path::new("index");
+        assert_eq!(index_file(index, "a"), index.join("1").join("a"));
+        assert_eq!(index_file(index, "ab"), index.join("2").join("ab"));
+        assert_eq!(
+            index_file(index, "Foo"),
+            index.join("3").join("f").join("foo")
+        );
+        assert_eq!(
+            index_file(index, "serde_json"),
+            index.join("se").join("rd").join("serde_json")
+        );
+    }
+
+    #[test]
+    fn test_alt_registry_list() {
+        let index = tempfile::tempdir().unwrap();
+        fs::create_dir_all(index.path().join("3").join("f")).unwrap();
+        fs::write(
+            index.path().join("3").join("f").join("foo"),
+            concat!(
+                r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"0a1b","features":{},"yanked":false}"#,
+                "\n",
+                r#"{"name":"foo","vers":"1.1.0","deps":[],"cksum":"00","features":{},"yanked":true}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.registries.insert(
+            "internal".into(),
+            RegistryConfig {
+                index: index.path().to_string_lossy().into_owned(),
+                download_url: "https://dl.example.com".into(),
+                crates: HashMap::new(),
+            },
+        );
+
+        let krate = AltRegistryCrate {
+            registry: "internal".into(),
+            name: "foo".into(),
+            version: "1.0.0".into(),
+            checksum: "0a1b".into(),
+        };
+        assert_eq!(
+            AltRegistryList::new(&config).fetch().unwrap(),
+            vec![Crate::AltRegistry(krate.clone())]
+        );
+        assert_eq!(
+            krate.download_url(&config).unwrap(),
+            "https://dl.example.com/foo/1.0.0/download"
+        );
+        assert_eq!(Crate::AltRegistry(krate).id(), "alt/internal/foo/1.0.0");
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_fetch_without_index() {
+        // The agents fetch the crates without a copy of the index of their registry
+        let mut tarball = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tarball
+            .append_data(&mut header, "foo-1.0.0/lib.rs", "//\n".as_bytes())
+            .unwrap();
+        let tarball = tarball.into_inner().unwrap().finish().unwrap();
+
+        // Serve the tarball to the two downloads below
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let body = tarball.clone();
+        let server = thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..len]);
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        let registry = format!("test-fetch-{}", port);
+        let mut config = Config::default();
+        config.registries.insert(
+            registry.clone(),
+            RegistryConfig {
+                index: "/nonexistent/index".into(),
+                download_url: format!("http://127.0.0.1:{}/{{crate}}-{{version}}.crate", port),
+                crates: HashMap::new(),
+            },
+        );
+
+        let tampered = AltRegistryCrate {
+            registry: registry.clone(),
+            name: "foo".into(),
+            version: "1.0.0".into(),
+            checksum: to_hex(&[0; 32]),
+        };
+        let err = tampered.fetch(&config).unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+
+        let krate = AltRegistryCrate {
+            checksum: to_hex(Sha256::digest(&tarball).as_slice()),
+            ..tampered
+        };
+        krate.fetch(&config).unwrap();
+        assert!(krate.source_dir().join("lib.rs").is_file());
+
+        server.join().unwrap();
+        fs::remove_dir_all(krate.source_dir().parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+use crate::dirs::WORK_DIR;
+use crate::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Only the branches and the tags are fetched, as GitHub also exposes every pull request as a ref.
+const REFSPECS: &[&str] = &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+
+/// A crate in an arbitrary git repository, optionally pinned to a revision.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, Clone)]
+pub struct GitRepo {
+    pub url: String,
+    pub rev: Option<String>,
+}
+
+impl GitRepo {
+    /// The name of the repository, without the path and the `.git` extension.
+    pub(crate) fn name(&self) -> &str {
+        let name = self.url.trim_end_matches('/').rsplit('/').next().unwrap();
+        name.trim_end_matches(".git")
+    }
+
+    /// A version of the URL that can be used as a directory name.
+    fn dir_name(&self) -> String {
+        sanitize(&self.url)
+    }
+
+    /// The directory the revision to test is checked out in.
+    pub(crate) fn checkout_dir(&self) -> PathBuf {
+        let mut name = self.dir_name();
+        if let Some(rev) = &self.rev {
+            name.push('@');
+            name.push_str(&sanitize(rev));
+        }
+        WORK_DIR.join("git-checkouts").join(name)
+    }
+
+    /// Fetch the repository and check out the revision to test, returning its sha.
+    pub(crate) fn fetch(&self) -> Fallible<String> {
+        self.fetch_rev(self.rev.as_ref().map(|rev| rev.as_str()))
+    }
+
+    /// Fetch the repository and check out `rev` instead of the revision of the crate.
+    pub(crate) fn fetch_rev(&self, rev: Option<&str>) -> Fallible<String> {
+        let cache = WORK_DIR.join("git-repos").join(self.dir_name());
+        self.fetch_to(rev, &cache, &self.checkout_dir())
+    }
+
+    fn fetch_to(&self, rev: Option<&str>, cache: &Path, checkout: &Path) -> Fallible<String> {
+        if cache.is_dir() {
+            let mut args = vec!["fetch", "--quiet", "--prune", "origin"];
+            args.extend_from_slice(REFSPECS);
+            git(cache, &args)?;
+        } else {
+            let parent = cache.parent().unwrap();
+            fs::create_dir_all(parent)?;
+            git(
+                parent,
+                &[
+                    "clone",
+                    "--quiet",
+                    "--bare",
+                    "--",
+                    &self.url,
+                    &cache.to_string_lossy(),
+                ],
+            )
+            .with_context(|_| format!("failed to clone {}", self.url))?;
+        }
+        // Allow the checkouts to fetch the commits that are not on a branch or a tag
+        git(cache, &["config", "uploadpack.allowAnySHA1InWant", "true"])?;
+
+        let rev = rev.unwrap_or("HEAD");
+        let resolve = || {
+            let commit = format!("{}^{{commit}}", rev);
+            git_output(cache, &["rev-parse", "--verify", "--quiet", &commit])
+        };
+        let sha = match resolve() {
+            Ok(sha) => sha,
+            // Commits outside of the branches and the tags, like the ones of pull requests, can
+            // still be fetched by their sha
+            Err(_) => git(cache, &["fetch", "--quiet", "--", "origin", rev])
+                .and_then(|_| resolve())
+                .with_context(|_| format!("revision {} not found in {}", rev, self.url))?,
+        };
+
+        if !checkout.join(".git").is_dir() {
+            let parent = checkout.parent().unwrap();
+            fs::create_dir_all(parent)?;
+            git(
+                parent,
+                &[
+                    "clone",
+                    "--quiet",
+                    "--no-checkout",
+                    "--",
+                    &cache.to_string_lossy(),
+                    &checkout.to_string_lossy(),
+                ],
+            )?;
+        }
+        git(checkout, &["fetch", "--quiet", "origin", &sha])?;
+        git(checkout, &["checkout", "--quiet", "--force", &sha])?;
+        git(checkout, &["clean", "--quiet", "-fdx"])?;
+
+        Ok(sha)
+    }
+}
+
+fn sanitize(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn git(cwd: &Path, args: &[&str]) -> Fallible<()> {
+    git_output(cwd, args).map(|_| ())
+}
+
+fn git_output(cwd: &Path, args: &[&str]) -> Fallible<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(cwd)
+        // Fail instead of waiting for credentials when a repository is private or missing
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "failed to run `git {}`: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{git, git_output, GitRepo};
+    use std::fs;
+    use std::path::Path;
+
+    fn commit(repo: &Path, content: &str) -> String {
+        fs::write(repo.join("README"), content).unwrap();
+        git(repo, &["add", "README"]).unwrap();
+        git(
+            repo,
+            &[
+                "-c",
+                "user.name=Crater",
+                "-c",
+                "user.email=crater@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                content,
+            ],
+        )
+        .unwrap();
+        git_output(repo, &["rev-parse", "HEAD"]).unwrap()
+    }
+
+    #[test]
+    fn test_git_repo_name() {
+        for url in &[
+            "https://gitlab.com/foo/bar",
+            "https://gitlab.com/foo/bar.git",
+            "https://gitlab.com/foo/bar/",
+            "/srv/git/bar.git",
+        ] {
+            let repo = GitRepo {
+                url: url.to_string(),
+                rev: None,
+            };
+            assert_eq!(repo.name(), "bar");
+        }
+    }
+
+    #[test]
+    fn test_fetch_local_repo() {
+        let tmp = tempfile::tempdir().unwrap();
+        let origin = tmp.path().join("origin");
+        fs::create_dir_all(&origin).unwrap();
+        git(&origin, &["init", "--quiet"]).unwrap();
+        let first = commit(&origin, "first");
+        let second = commit(&origin, "second");
+
+        // Local bare repositories are supported as well
+        let bare = tmp.path().join("bare.git");
+        git(
+            tmp.path(),
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                &origin.to_string_lossy(),
+                &bare.to_string_lossy(),
+            ],
+        )
+        .unwrap();
+
+        let head = GitRepo {
+            url: bare.to_string_lossy().into_owned(),
+            rev: None,
+        };
+        let checkout = tmp.path().join("head");
+        assert_eq!(
+            head.fetch_to(None, &tmp.path().join("cache"), &checkout)
+                .unwrap(),
+            second
+        );
+        assert_eq!(
+            fs::read_to_string(checkout.join("README")).unwrap(),
+            "second"
+        );
+
+        let pinned = GitRepo {
+            url: bare.to_string_lossy().into_owned(),
+            rev: Some(first[..8].to_string()),
+        };
+        let checkout = tmp.path().join("pinned");
+        assert_eq!(
+            pinned
+                .fetch_to(
+                    pinned.rev.as_ref().map(|r| r.as_str()),
+                    &tmp.path().join("cache"),
+                    &checkout
+                )
+                .unwrap(),
+            first
+        );
+        assert_eq!(
+            fs::read_to_string(checkout.join("README")).unwrap(),
+            "first"
+        );
+
+        // URLs are never parsed as options
+        let option = GitRepo {
+            url: format!("--upload-pack=touch {}", tmp.path().join("pwned").display()),
+            rev: None,
+        };
+        assert!(option
+            .fetch_to(
+                None,
+                &tmp.path().join("option"),
+                &tmp.path().join("option-checkout")
+            )
+            .is_err());
+        assert!(!tmp.path().join("pwned").exists());
+
+        let missing = GitRepo {
+            url: bare.to_string_lossy().into_owned(),
+            rev: Some("missing".into()),
+        };
+        assert!(missing
+            .fetch_to(
+                Some("missing"),
+                &tmp.path().join("cache"),
+                &tmp.path().join("missing")
+            )
+            .is_err());
+
+        // Checking out another revision in an existing checkout
+        let checkout = tmp.path().join("head");
+        assert_eq!(
+            head.fetch_to(Some(first.as_str()), &tmp.path().join("cache"), &checkout)
+                .unwrap(),
+            first
+        );
+        assert_eq!(
+            fs::read_to_string(checkout.join("README")).unwrap(),
+            "first"
+        );
+
+        // Commits of pull requests are fetched by their sha, without fetching all of them
+        git(&origin, &["checkout", "--quiet", "-b", "pr"]).unwrap();
+        let pr = commit(&origin, "pr");
+        git(
+            &origin,
+            &[
+                "push",
+                "--quiet",
+                &bare.to_string_lossy(),
+                "pr:refs/pull/1/head",
+            ],
+        )
+        .unwrap();
+        git(
+            &bare,
+            &["config", "uploadpack.allowReachableSHA1InWant", "true"],
+        )
+        .unwrap();
+        assert_eq!(
+            head.fetch_to(Some(pr.as_str()), &tmp.path().join("cache"), &checkout)
+                .unwrap(),
+            pr
+        );
+        assert_eq!(fs::read_to_string(checkout.join("README")).unwrap(), "pr");
+        assert_eq!(
+            git_output(&tmp.path().join("cache"), &["for-each-ref", "refs/pull"]).unwrap(),
+            ""
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use crate::crates::{lists::List, Crate};
+use crate::config::Config;
+use crate::crates::{lists::List, Crate, GitRepo};
 use crate::prelude::*;
 use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
 use std::str::FromStr;
 
 static CACHED_LIST: &str =
@@ -17,10 +20,15 @@ pub(crate) struct GitHubList {
     source: Cow<'static, str>,
 }
 
-impl Default for GitHubList {
-    fn default() -> Self {
+impl GitHubList {
+    /// Use the local CSV file configured in `config.toml` if there is one, or the cached list
+    /// otherwise.
+    pub(crate) fn new(config: &Config) -> Self {
         GitHubList {
-            source: CACHED_LIST.into(),
+            source: match &config.lists.github_csv {
+                Some(path) => path.to_string_lossy().into_owned().into(),
+                None => CACHED_LIST.into(),
+            },
         }
     }
 }
@@ -31,9 +39,19 @@ impl List for GitHubList {
     fn fetch(&self) -> Fallible<Vec<Crate>> {
         info!("loading cached GitHub list from {}", self.source);
 
-        let mut resp = crate::utils::http::get_sync(&self.source)
-            .with_context(|_| format!("failed to fetch GitHub crates list from {}", self.source))?;
-        let mut reader = ::csv::Reader::from_reader(&mut resp);
+        let source: Box<dyn Read> =
+            if self.source.starts_with("https://") || self.source.starts_with("http://") {
+                Box::new(
+                    crate::utils::http::get_sync(&self.source).with_context(|_| {
+                        format!("failed to fetch GitHub crates list from {}", self.source)
+                    })?,
+                )
+            } else {
+                Box::new(File::open(&*self.source).with_context(|_| {
+                    format!("failed to open GitHub crates list {}", self.source)
+                })?)
+            };
+        let mut reader = ::csv::Reader::from_reader(source);
 
         let mut list = Vec::new();
         for line in reader.deserialize() {
@@ -73,6 +91,14 @@ impl GitHubRepo {
     pub(crate) fn slug(&self) -> String {
         format!("{}/{}", self.org, self.name)
     }
+
+    /// The repository as a generic git crate, used to check out pinned commits.
+    pub(crate) fn git_repo(&self) -> GitRepo {
+        GitRepo {
+            url: format!("https://github.com/{}/{}", self.org, self.name),
+            rev: None,
+        }
+    }
 }
 
 impl FromStr for GitHubRepo {
@@ -93,3 +119,35 @@ impl FromStr for GitHubRepo {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GitHubList;
+    use crate::config::Config;
+    use crate::crates::{lists::List, Crate, GitHubRepo};
+    use crate::db::Database;
+    use std::io::Write;
+
+    #[test]
+    fn test_local_github_list() {
+        let mut csv = tempfile::NamedTempFile::new().unwrap();
+        writeln!(csv, "name,has_cargo_toml,has_cargo_lock").unwrap();
+        writeln!(csv, "brson/hello-rs,true,true").unwrap();
+        writeln!(csv, "rust-lang/rust-by-example,true,false").unwrap();
+        writeln!(csv, "malformed,true,true").unwrap();
+        csv.flush().unwrap();
+
+        let mut config = Config::default();
+        config.lists.github_csv = Some(csv.path().into());
+
+        let db = Database::temp().unwrap();
+        GitHubList::new(&config).update(&db).unwrap();
+        assert_eq!(
+            GitHubList::get(&db).unwrap(),
+            vec![Crate::GitHub(GitHubRepo {
+                org: "brson".into(),
+                name: "hello-rs".into(),
+            })]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub(in crate::crates) mod alt_registry;
+pub(in crate::crates) mod git;
 pub(in crate::crates) mod github;
 pub(in crate::crates) mod local;
 pub(in crate::crates) mod registry;
@@ -1,55 +1,267 @@
+use crate::config::Config;
 use crate::crates::{lists::List, Crate};
+use crate::db::{Database, QueryUtils};
 use crate::dirs::WORK_DIR;
 use crate::prelude::*;
-use crates_index::Index;
-use std::collections::HashMap;
+use crates_index::{Crate as IndexCrate, DependencyKind, Index, Version};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self};
+use std::path::PathBuf;
 
-pub(crate) struct RegistryList;
+/// The location of the crates.io index, either the checkout managed by Crater or a snapshot
+/// configured in `config.toml`.
+struct IndexSource {
+    snapshot: Option<PathBuf>,
+}
+
+impl IndexSource {
+    fn new(config: &Config) -> Self {
+        IndexSource {
+            snapshot: config.lists.registry_index.clone(),
+        }
+    }
+
+    /// Open the index, fetching it first unless a snapshot is used.
+    fn fetch(&self) -> Fallible<Index> {
+        if let Some(snapshot) = &self.snapshot {
+            if !snapshot.is_dir() {
+                bail!("missing crates.io index snapshot: {}", snapshot.display());
+            }
+            info!(
+                "loading crates.io index snapshot from {}",
+                snapshot.display()
+            );
+            return Ok(Index::new(snapshot));
+        }
+
+        fs::create_dir_all(&*WORK_DIR)?;
+        let index = Index::new(WORK_DIR.join("crates.io-index"));
+        index.retrieve_or_update().to_failure()?;
+        Ok(index)
+    }
+
+    /// Open the index without fetching it.
+    fn open(&self) -> Index {
+        match &self.snapshot {
+            Some(snapshot) => Index::new(snapshot),
+            None => Index::new(WORK_DIR.join("crates.io-index")),
+        }
+    }
+}
+
+pub(crate) struct RegistryList {
+    index: IndexSource,
+    /// The normal and build dependencies of the crates, collected while fetching the list.
+    dependencies: RefCell<Vec<(String, String)>>,
+}
+
+impl RegistryList {
+    pub(crate) fn new(config: &Config) -> Self {
+        RegistryList {
+            index: IndexSource::new(config),
+            dependencies: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Store the dependencies of the crates in the list, to allow building the dependency graph
+    /// of the registry. The list must already be fetched.
+    pub(crate) fn update_dependencies(&self, db: &Database) -> Fallible<()> {
+        let dependencies = self.dependencies.borrow();
+        if dependencies.is_empty() {
+            bail!("the crates.io list must be fetched before storing its dependencies");
+        }
+
+        db.transaction(|t| {
+            t.execute("DELETE FROM registry_dependencies;", &[])?;
+            for (krate, dependency) in dependencies.iter() {
+                t.execute_cached(
+                    "INSERT INTO registry_dependencies (crate, dependency) VALUES (?1, ?2);",
+                    &[krate, dependency],
+                )?;
+            }
+
+            Ok(())
+        })?;
+
+        info!(
+            "loaded {} dependencies of the registry crates",
+            dependencies.len()
+        );
+        Ok(())
+    }
+
+    /// Every published version of the named crates that was not yanked. The index must already
+    /// be fetched.
+    pub(crate) fn all_versions_of(&self, names: &[String]) -> Fallible<Vec<Crate>> {
+        let index = self.index.open();
+        let mut missing = names.iter().map(|n| n.as_str()).collect::<HashSet<_>>();
+
+        let mut list = Vec::new();
+        for krate in index.crates() {
+            if !missing.remove(krate.name()) {
+                continue;
+            }
+
+            for version in krate.versions().iter().filter(|v| !v.is_yanked()) {
+                list.push(Crate::Registry(RegistryCrate {
+                    name: krate.name().to_string(),
+                    version: version.version().to_string(),
+                }));
+            }
+        }
+
+        if !missing.is_empty() {
+            bail!("missing crates.io crates: {:?}", missing);
+        }
+
+        Ok(list)
+    }
+}
+
+/// The latest version of a crate that was not yanked.
+pub(in crate::crates) fn latest_version(krate: &IndexCrate) -> Option<&Version> {
+    // The versions() method returns the list of published versions starting from the first one,
+    // so its output is reversed to check the latest first. If all the versions are yanked the
+    // crate is automatically skipped
+    krate.versions().iter().rev().find(|v| !v.is_yanked())
+}
+
+/// The numeric part of a version, or `None` for pre-releases and malformed versions.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    // Build metadata doesn't affect the precedence of versions
+    let version = version.splitn(2, '+').next()?;
+    let mut parts = version.split('.').map(|part| part.parse().ok());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) => {
+            Some((major, minor, patch))
+        }
+        _ => None,
+    }
+}
+
+/// The semver-compatible release line a version belongs to, following Cargo's rules: `1.2.3`
+/// belongs to `1`, `0.2.3` to `0.2` and `0.0.3` to `0.0.3`.
+fn compatibility_line((major, minor, patch): (u64, u64, u64)) -> (u64, u64, u64) {
+    if major > 0 {
+        (major, 0, 0)
+    } else if minor > 0 {
+        (0, minor, 0)
+    } else {
+        (0, 0, patch)
+    }
+}
+
+/// Sort the list of registry crates by their number of dependents.
+fn sort_by_popularity(list: &mut Vec<Crate>, counts: &HashMap<String, u32>) {
+    // The sort is stable, so multiple versions of the same crate are kept together
+    list.sort_by(|a, b| {
+        if let (Crate::Registry(ref a), Crate::Registry(ref b)) = (a, b) {
+            let count_a = counts.get(&a.name).cloned().unwrap_or(0);
+            let count_b = counts.get(&b.name).cloned().unwrap_or(0);
+            count_b.cmp(&count_a)
+        } else {
+            panic!("non-registry crate produced in the registry list");
+        }
+    });
+}
+
+fn count_dependents(index: &Index) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for krate in index.crates() {
+        if let Some(version) = latest_version(&krate) {
+            for dependency in version.dependencies() {
+                let count = counts.entry(dependency.name().to_string()).or_insert(0);
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
 
 impl List for RegistryList {
     const NAME: &'static str = "registry";
 
     fn fetch(&self) -> Fallible<Vec<Crate>> {
         let mut list = Vec::new();
-        let mut counts = HashMap::new();
+        let mut dependencies = Vec::new();
 
-        fs::create_dir_all(&*WORK_DIR)?;
-        let index = Index::new(WORK_DIR.join("crates.io-index"));
-        index.retrieve_or_update().to_failure()?;
+        let index = self.index.fetch()?;
 
         for krate in index.crates() {
-            // The versions() method returns the list of published versions starting from the
-            // first one, so its output is reversed to check the latest first
-            for version in krate.versions().iter().rev() {
-                // Try every version until we find a non-yanked one. If all the versions are
-                // yanked the crate is automatically skipped
-                if !version.is_yanked() {
-                    // Increment the counters of this crate's dependencies
-                    for dependency in version.dependencies() {
-                        let count = counts.entry(dependency.name().to_string()).or_insert(0);
-                        *count += 1;
+            if let Some(version) = latest_version(&krate) {
+                for dependency in version.dependencies() {
+                    // Dev-dependencies are not built when the crate is used by other crates, so
+                    // they can't cause regressions in its dependents
+                    if dependency.kind() != DependencyKind::Dev {
+                        dependencies
+                            .push((krate.name().to_string(), dependency.name().to_string()));
                     }
-
-                    list.push(Crate::Registry(RegistryCrate {
-                        name: krate.name().to_string(),
-                        version: version.version().to_string(),
-                    }));
-                    break;
                 }
+
+                list.push(Crate::Registry(RegistryCrate {
+                    name: krate.name().to_string(),
+                    version: version.version().to_string(),
+                }));
             }
         }
 
         // Ensure the list is sorted by popularity
-        list.sort_by(|a, b| {
-            if let (Crate::Registry(ref a), Crate::Registry(ref b)) = (a, b) {
-                let count_a = counts.get(&a.name).cloned().unwrap_or(0);
-                let count_b = counts.get(&b.name).cloned().unwrap_or(0);
-                count_b.cmp(&count_a)
-            } else {
-                panic!("non-registry crate produced in the registry list");
+        sort_by_popularity(&mut list, &count_dependents(&index));
+        *self.dependencies.borrow_mut() = dependencies;
+
+        Ok(list)
+    }
+}
+
+/// The latest version of every semver-compatible release line of the crates.io crates, to test
+/// older releases that are still widely used.
+pub(crate) struct RegistryVersionsList {
+    index: IndexSource,
+}
+
+impl RegistryVersionsList {
+    pub(crate) fn new(config: &Config) -> Self {
+        RegistryVersionsList {
+            index: IndexSource::new(config),
+        }
+    }
+}
+
+impl List for RegistryVersionsList {
+    const NAME: &'static str = "registry-versions";
+
+    fn fetch(&self) -> Fallible<Vec<Crate>> {
+        let mut list = Vec::new();
+
+        let index = self.index.fetch()?;
+
+        for krate in index.crates() {
+            // Versions are not necessarily published in order, as fixes can be backported to
+            // older release lines
+            let mut lines = HashMap::new();
+            for version in krate.versions().iter().filter(|v| !v.is_yanked()) {
+                if let Some(parsed) = parse_version(version.version()) {
+                    let latest = lines
+                        .entry(compatibility_line(parsed))
+                        .or_insert((parsed, version));
+                    if parsed > latest.0 {
+                        *latest = (parsed, version);
+                    }
+                }
             }
-        });
+
+            let mut lines = lines.into_iter().collect::<Vec<_>>();
+            lines.sort_by_key(|&(line, _)| ::std::cmp::Reverse(line));
+            for (_, (_, version)) in lines {
+                list.push(Crate::Registry(RegistryCrate {
+                    name: krate.name().to_string(),
+                    version: version.version().to_string(),
+                }));
+            }
+        }
+
+        sort_by_popularity(&mut list, &count_dependents(&index));
 
         Ok(list)
     }
@@ -60,3 +272,120 @@ pub struct RegistryCrate {
     pub name: String,
     pub version: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{compatibility_line, parse_version, RegistryList, RegistryVersionsList};
+    use crate::config::Config;
+    use crate::crates::{lists::List, Crate, RegistryCrate};
+    use crate::db::{Database, QueryUtils};
+    use std::fs;
+
+    #[test]
+    fn test_registry_snapshot() {
+        let index = tempfile::tempdir().unwrap();
+        let versions = |name: &str, versions: &[&str]| {
+            versions
+                .iter()
+                .map(|version| {
+                    format!(
+                        r#"{{"name":"{}","vers":"{}","deps":[],"cksum":"00","features":{{}},"yanked":false}}"#,
+                        name, version
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        fs::create_dir_all(index.path().join("3").join("f")).unwrap();
+        fs::write(
+            index.path().join("3").join("f").join("foo"),
+            versions("foo", &["0.1.0", "0.1.1", "1.0.0"]),
+        )
+        .unwrap();
+        let dependency = |name: &str, kind: &str| {
+            format!(
+                r#"{{"name":"{}","req":"^1.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"{}"}}"#,
+                name, kind
+            )
+        };
+        fs::create_dir_all(index.path().join("3").join("b")).unwrap();
+        fs::write(
+            index.path().join("3").join("b").join("bar"),
+            format!(
+                r#"{{"name":"bar","vers":"0.1.0","deps":[{},{},{}],"cksum":"00","features":{{}},"yanked":false}}"#,
+                dependency("foo", "normal"),
+                dependency("cc", "build"),
+                dependency("quickcheck", "dev"),
+            ),
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.lists.registry_index = Some(index.path().into());
+
+        let db = Database::temp().unwrap();
+        let registry = RegistryList::new(&config);
+        // The dependencies are collected while fetching the list
+        assert!(registry.update_dependencies(&db).is_err());
+        registry.update(&db).unwrap();
+        registry.update_dependencies(&db).unwrap();
+        RegistryVersionsList::new(&config).update(&db).unwrap();
+
+        let krate = |version: &str| {
+            Crate::Registry(RegistryCrate {
+                name: "foo".into(),
+                version: version.into(),
+            })
+        };
+        let bar = Crate::Registry(RegistryCrate {
+            name: "bar".into(),
+            version: "0.1.0".into(),
+        });
+        // The crates with the most dependents come first
+        assert_eq!(
+            RegistryList::get(&db).unwrap(),
+            vec![krate("1.0.0"), bar.clone()]
+        );
+        assert_eq!(
+            RegistryVersionsList::get(&db).unwrap(),
+            vec![krate("1.0.0"), krate("0.1.1"), bar]
+        );
+
+        let mut dependencies = db
+            .query(
+                "SELECT crate, dependency FROM registry_dependencies;",
+                &[],
+                |row| {
+                    (
+                        row.get::<_, String>("crate"),
+                        row.get::<_, String>("dependency"),
+                    )
+                },
+            )
+            .unwrap();
+        dependencies.sort();
+        assert_eq!(
+            dependencies,
+            vec![
+                ("bar".to_string(), "cc".to_string()),
+                ("bar".to_string(), "foo".to_string()),
+            ]
+        );
+
+        // Snapshots are never fetched, so missing ones are an error
+        config.lists.registry_index = Some(index.path().join("missing"));
+        assert!(RegistryList::new(&config).fetch().is_err());
+    }
+
+    #[test]
+    fn test_compatibility_line() {
+        let line = |version| parse_version(version).map(compatibility_line);
+
+        assert_eq!(line("1.0.89"), Some((1, 0, 0)));
+        assert_eq!(line("2.3.0+build.1"), Some((2, 0, 0)));
+        assert_eq!(line("0.15.44"), Some((0, 15, 0)));
+        assert_eq!(line("0.0.3"), Some((0, 0, 3)));
+        assert_eq!(line("1.0.0-alpha.1"), None);
+        assert_eq!(line("1.0"), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::crates::{Crate, GitHubRepo};
 use crate::prelude::*;
 use rand::{self, distributions::Alphanumeric, Rng};
 use rusqlite::{Connection, Transaction};
@@ -304,6 +305,392 @@ fn migrations() -> Vec<(&'static str, MigrationKind)> {
         ),
     ));
 
+    migrations.push((
+        "add_result_attempts_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE result_attempts (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+
+                PRIMARY KEY (experiment, crate, toolchain) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "make_toolchain_end_optional",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE experiments_new (
+                name TEXT PRIMARY KEY,
+                mode TEXT NOT NULL,
+                cap_lints TEXT NOT NULL,
+
+                toolchain_start TEXT NOT NULL,
+                toolchain_end TEXT,
+
+                created_at DATETIME NOT NULL,
+                started_at DATETIME,
+                completed_at DATETIME,
+
+                priority INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                report_url TEXT,
+                github_issue TEXT,
+                github_issue_url TEXT,
+                github_issue_number INTEGER,
+                assigned_to TEXT,
+                ignore_blacklist INTEGER NOT NULL DEFAULT 0,
+                requirement TEXT DEFAULT 'linux'
+            );
+
+            INSERT INTO experiments_new (
+                name, mode, cap_lints, toolchain_start, toolchain_end, created_at, started_at,
+                completed_at, priority, status, report_url, github_issue, github_issue_url,
+                github_issue_number, assigned_to, ignore_blacklist, requirement
+            ) SELECT
+                name, mode, cap_lints, toolchain_start, toolchain_end, created_at, started_at,
+                completed_at, priority, status, report_url, github_issue, github_issue_url,
+                github_issue_number, assigned_to, ignore_blacklist, requirement
+            FROM experiments;
+
+            DROP TABLE experiments;
+            ALTER TABLE experiments_new RENAME TO experiments;
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "store_toolchains_as_list",
+        MigrationKind::Code(Box::new(|t| {
+            let fn_name = format!(
+                "crater_migration__{}",
+                rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(10)
+                    .collect::<String>()
+            );
+            t.create_scalar_function(&fn_name, 2, true, |ctx| {
+                let mut toolchains = vec![ctx.get::<String>(0)?];
+                if let Some(end) = ctx.get::<Option<String>>(1)? {
+                    toolchains.push(end);
+                }
+
+                serde_json::to_string(&toolchains)
+                    .map_err(|e| ::rusqlite::Error::UserFunctionError(e.into()))
+            })?;
+
+            t.execute_batch(&format!(
+                "
+                CREATE TABLE experiments_new (
+                    name TEXT PRIMARY KEY,
+                    mode TEXT NOT NULL,
+                    cap_lints TEXT NOT NULL,
+
+                    toolchains TEXT NOT NULL,
+
+                    created_at DATETIME NOT NULL,
+                    started_at DATETIME,
+                    completed_at DATETIME,
+
+                    priority INTEGER NOT NULL,
+                    status TEXT NOT NULL,
+                    report_url TEXT,
+                    github_issue TEXT,
+                    github_issue_url TEXT,
+                    github_issue_number INTEGER,
+                    assigned_to TEXT,
+                    ignore_blacklist INTEGER NOT NULL DEFAULT 0,
+                    requirement TEXT DEFAULT 'linux'
+                );
+
+                INSERT INTO experiments_new (
+                    name, mode, cap_lints, toolchains, created_at, started_at, completed_at,
+                    priority, status, report_url, github_issue, github_issue_url,
+                    github_issue_number, assigned_to, ignore_blacklist, requirement
+                ) SELECT
+                    name, mode, cap_lints, {}(toolchain_start, toolchain_end), created_at,
+                    started_at, completed_at, priority, status, report_url, github_issue,
+                    github_issue_url, github_issue_number, assigned_to, ignore_blacklist,
+                    requirement
+                FROM experiments;
+
+                DROP TABLE experiments;
+                ALTER TABLE experiments_new RENAME TO experiments;
+                ",
+                fn_name
+            ))?;
+
+            Ok(())
+        })),
+    ));
+
+    migrations.push((
+        "add_bisections_tables",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE bisections (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                start_result TEXT NOT NULL,
+                last_good TEXT NOT NULL,
+                first_bad TEXT NOT NULL,
+                status TEXT NOT NULL,
+
+                PRIMARY KEY (experiment, crate) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+
+            CREATE TABLE bisection_results (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                result TEXT NOT NULL,
+                log BLOB NOT NULL,
+                encoding TEXT NOT NULL,
+
+                PRIMARY KEY (experiment, crate, toolchain) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "create_registry_dependencies_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE registry_dependencies (
+                crate TEXT NOT NULL,
+                dependency TEXT NOT NULL,
+
+                PRIMARY KEY (crate, dependency) ON CONFLICT REPLACE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_experiment_field_crates_seed",
+        MigrationKind::SQL(
+            "
+            ALTER TABLE experiments ADD COLUMN crates_seed INTEGER;
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "create_crate_downloads_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE crate_downloads (
+                crate TEXT PRIMARY KEY ON CONFLICT REPLACE,
+                downloads INTEGER NOT NULL
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "store_shas_of_all_crates",
+        MigrationKind::Code(Box::new(|t| {
+            let fn_name = format!(
+                "crater_migration__{}",
+                rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(10)
+                    .collect::<String>()
+            );
+            t.create_scalar_function(&fn_name, 2, true, |ctx| {
+                let krate = Crate::GitHub(GitHubRepo {
+                    org: ctx.get(0)?,
+                    name: ctx.get(1)?,
+                });
+
+                serde_json::to_string(&krate)
+                    .map_err(|e| ::rusqlite::Error::UserFunctionError(e.into()))
+            })?;
+
+            t.execute_batch(&format!(
+                "
+                CREATE TABLE shas_new (
+                    experiment TEXT NOT NULL,
+                    crate TEXT NOT NULL,
+                    sha TEXT NOT NULL,
+
+                    PRIMARY KEY (experiment, crate) ON CONFLICT REPLACE,
+                    FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+                );
+
+                INSERT INTO shas_new (experiment, crate, sha)
+                    SELECT experiment, {}(org, name), sha FROM shas ORDER BY rowid;
+
+                DROP TABLE shas;
+                ALTER TABLE shas_new RENAME TO shas;
+                ",
+                fn_name
+            ))?;
+
+            Ok(())
+        })),
+    ));
+
+    migrations.push((
+        "store_list_snapshots",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE list_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                list TEXT NOT NULL,
+                loaded_at DATETIME NOT NULL
+            );
+
+            CREATE INDEX list_snapshots__list ON list_snapshots (list);
+
+            INSERT INTO list_snapshots (list, loaded_at)
+                SELECT list, MAX(loaded_at) FROM crates GROUP BY list ORDER BY MIN(rowid);
+
+            CREATE TABLE crates_new (
+                crate TEXT NOT NULL,
+                snapshot INTEGER NOT NULL,
+
+                PRIMARY KEY (snapshot, crate) ON CONFLICT REPLACE,
+                FOREIGN KEY (snapshot) REFERENCES list_snapshots(id) ON DELETE CASCADE
+            );
+
+            INSERT INTO crates_new (crate, snapshot)
+                SELECT crates.crate, list_snapshots.id FROM crates
+                INNER JOIN list_snapshots ON list_snapshots.list = crates.list
+                ORDER BY crates.rowid;
+
+            DROP TABLE crates;
+            ALTER TABLE crates_new RENAME TO crates;
+
+            CREATE TABLE experiment_list_snapshots (
+                experiment TEXT NOT NULL,
+                snapshot INTEGER NOT NULL,
+
+                PRIMARY KEY (experiment, snapshot) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE,
+                FOREIGN KEY (snapshot) REFERENCES list_snapshots(id) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_experiment_field_feature_matrix",
+        MigrationKind::SQL(
+            "
+            ALTER TABLE experiments ADD COLUMN feature_matrix INTEGER NOT NULL DEFAULT 0;
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_experiment_field_targets",
+        MigrationKind::SQL(
+            "
+            ALTER TABLE experiments ADD COLUMN targets TEXT NOT NULL DEFAULT '[]';
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_result_metrics_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE result_metrics (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                step TEXT NOT NULL,
+                duration INTEGER NOT NULL,
+                target_dir_size INTEGER,
+
+                PRIMARY KEY (experiment, crate, toolchain, step) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_result_diagnostics_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE result_diagnostics (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                code TEXT NOT NULL,
+                level TEXT NOT NULL,
+                count INTEGER NOT NULL,
+
+                PRIMARY KEY (experiment, crate, toolchain, code, level) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_result_future_incompats_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE result_future_incompats (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                lint TEXT NOT NULL,
+                package TEXT NOT NULL,
+
+                PRIMARY KEY (experiment, crate, toolchain, lint, package) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_result_unstable_features_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE result_unstable_features (
+                experiment TEXT NOT NULL,
+                crate TEXT NOT NULL,
+                toolchain TEXT NOT NULL,
+                feature TEXT NOT NULL,
+                file TEXT NOT NULL,
+
+                PRIMARY KEY (experiment, crate, toolchain, feature, file) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
+    migrations.push((
+        "add_bisection_probes_table",
+        MigrationKind::SQL(
+            "
+            CREATE TABLE bisection_probes (
+                experiment TEXT NOT NULL,
+                parent TEXT NOT NULL,
+
+                PRIMARY KEY (experiment) ON CONFLICT REPLACE,
+                FOREIGN KEY (experiment) REFERENCES experiments(name) ON DELETE CASCADE,
+                FOREIGN KEY (parent) REFERENCES experiments(name) ON DELETE CASCADE
+            );
+            ",
+        ),
+    ));
+
     migrations
 }
 
@@ -328,6 +715,11 @@ pub fn execute(db: &mut Connection) -> Fallible<()> {
         result
     };
 
+    // Rebuilding a table is the only way to change its columns in SQLite, but dropping the old
+    // table with foreign keys enabled would cascade the deletion to every table referencing it.
+    // The pragma is a no-op inside a transaction, so it has to be toggled around all of them.
+    db.execute("PRAGMA foreign_keys = OFF;", no_args())?;
+
     for &(name, ref migration) in &migrations() {
         if !executed_migrations.contains(&name.to_string()) {
             let t = db.transaction()?;
@@ -344,5 +736,7 @@ pub fn execute(db: &mut Connection) -> Fallible<()> {
         }
     }
 
+    db.execute("PRAGMA foreign_keys = ON;", no_args())?;
+
     Ok(())
 }
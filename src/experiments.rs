@@ -1,8 +1,9 @@
 use crate::crates::Crate;
 use crate::db::{Database, QueryUtils};
 use crate::prelude::*;
-use crate::toolchain::Toolchain;
+use crate::toolchain::{FeatureSet, Toolchain};
 use chrono::{DateTime, Utc};
+use rand::{thread_rng, Rng};
 use rusqlite::Row;
 use serde_json;
 use std::fmt;
@@ -22,18 +23,190 @@ string_enum!(pub enum Mode {
     BuildAndTest => "build-and-test",
     BuildOnly => "build-only",
     CheckOnly => "check-only",
+    Doctest => "doctest",
+    BenchCheck => "bench-check",
     Clippy => "clippy",
     Rustdoc => "rustdoc",
+    Miri => "miri",
+    Rustfmt => "rustfmt",
+    RustfmtIdempotence => "rustfmt-idempotence",
     UnstableFeatures => "unstable-features",
 });
 
-string_enum!(pub enum CrateSelect {
-    Full => "full",
-    Demo => "demo",
-    SmallRandom => "small-random",
-    Top100 => "top-100",
-    Local => "local",
-});
+const SMALL_RANDOM_COUNT: u32 = 20;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum CrateSelect {
+    Full,
+    Demo,
+    /// A random sample of crates, reproducible when the seed is known.
+    Random {
+        count: u32,
+        seed: Option<u32>,
+    },
+    /// The most popular crates.io crates.
+    Top(u32),
+    /// A percentage of the most popular crates.io crates.
+    Percent(u32),
+    Local,
+    /// The crates that regressed in another experiment.
+    Regressed(String),
+    /// An explicit list of crates, either names of crates.io crates or GitHub repos, or
+    /// anything accepted by `Crate::from_str`.
+    List(Vec<String>),
+    /// The crates published on an alternative registry defined in the configuration.
+    AltRegistry(String),
+    /// The latest version of every semver-compatible release line of the crates.io crates.
+    AllVersions,
+    /// Every published version of the named crates.io crates.
+    Versions(Vec<String>),
+}
+
+impl fmt::Display for CrateSelect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrateSelect::Full => write!(f, "full"),
+            CrateSelect::Demo => write!(f, "demo"),
+            CrateSelect::Random {
+                count,
+                seed: Some(seed),
+            } => write!(f, "random-{}:{}", count, seed),
+            CrateSelect::Random { count, seed: None } => write!(f, "random-{}", count),
+            CrateSelect::Top(count) => write!(f, "top-{}", count),
+            CrateSelect::Percent(percent) => write!(f, "percent-{}", percent),
+            CrateSelect::Local => write!(f, "local"),
+            CrateSelect::Regressed(ref ex) => write!(f, "regressed:{}", ex),
+            CrateSelect::List(ref specs) => write!(f, "list:{}", specs.join(",")),
+            CrateSelect::AltRegistry(ref name) => write!(f, "registry:{}", name),
+            CrateSelect::AllVersions => write!(f, "all-versions"),
+            CrateSelect::Versions(ref names) => write!(f, "versions:{}", names.join(",")),
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+#[cfg_attr(test, derive(PartialEq, Eq))]
+pub enum CrateSelectParseError {
+    #[fail(display = "the crate selection is empty")]
+    Empty,
+    #[fail(display = "unexpected crate selection payload")]
+    UnexpectedPayload,
+    #[fail(display = "invalid crate selection: {}", _0)]
+    InvalidKind(String),
+    #[fail(display = "invalid number in the crate selection: {}", _0)]
+    InvalidNumber(String),
+}
+
+impl CrateSelect {
+    /// Pick the seed of random selections, so that it can be stored with the experiment.
+    pub(crate) fn with_seed(self) -> Self {
+        match self {
+            CrateSelect::Random { count, seed: None } => CrateSelect::Random {
+                count,
+                seed: Some(thread_rng().gen()),
+            },
+            other => other,
+        }
+    }
+
+    pub(crate) fn seed(&self) -> Option<u32> {
+        match *self {
+            CrateSelect::Random { seed, .. } => seed,
+            _ => None,
+        }
+    }
+}
+
+fn parse_names(payload: Option<&str>) -> Result<Vec<String>, CrateSelectParseError> {
+    let names = payload
+        .ok_or(CrateSelectParseError::Empty)?
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        return Err(CrateSelectParseError::Empty);
+    }
+
+    Ok(names)
+}
+
+fn parse_number(input: &str) -> Result<u32, CrateSelectParseError> {
+    match input.parse() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(CrateSelectParseError::InvalidNumber(input.into())),
+    }
+}
+
+impl FromStr for CrateSelect {
+    type Err = CrateSelectParseError;
+
+    fn from_str(input: &str) -> Result<Self, CrateSelectParseError> {
+        if input.trim().is_empty() {
+            return Err(CrateSelectParseError::Empty);
+        }
+
+        let mut split = input.splitn(2, ':');
+        let kind = split.next().ok_or(CrateSelectParseError::Empty)?;
+        let payload = split.next();
+
+        let select = match kind {
+            "regressed" => {
+                let ex = payload.ok_or(CrateSelectParseError::Empty)?;
+                if ex.trim().is_empty() {
+                    return Err(CrateSelectParseError::Empty);
+                }
+
+                return Ok(CrateSelect::Regressed(ex.to_string()));
+            }
+            "registry" => {
+                let name = payload.ok_or(CrateSelectParseError::Empty)?;
+                if name.trim().is_empty() {
+                    return Err(CrateSelectParseError::Empty);
+                }
+
+                return Ok(CrateSelect::AltRegistry(name.to_string()));
+            }
+            "list" => return Ok(CrateSelect::List(parse_names(payload)?)),
+            "versions" => return Ok(CrateSelect::Versions(parse_names(payload)?)),
+            "small-random" => CrateSelect::Random {
+                count: SMALL_RANDOM_COUNT,
+                seed: None,
+            },
+            random if random.starts_with("random-") => {
+                let count = parse_number(&random["random-".len()..])?;
+                let seed = match payload {
+                    Some(seed) => Some(
+                        seed.parse()
+                            .map_err(|_| CrateSelectParseError::InvalidNumber(seed.into()))?,
+                    ),
+                    None => None,
+                };
+
+                return Ok(CrateSelect::Random { count, seed });
+            }
+            top if top.starts_with("top-") => CrateSelect::Top(parse_number(&top["top-".len()..])?),
+            percent if percent.starts_with("percent-") => {
+                let percent = &percent["percent-".len()..];
+                match parse_number(percent)? {
+                    number if number <= 100 => CrateSelect::Percent(number),
+                    _ => return Err(CrateSelectParseError::InvalidNumber(percent.into())),
+                }
+            }
+            "full" => CrateSelect::Full,
+            "demo" => CrateSelect::Demo,
+            "local" => CrateSelect::Local,
+            "all-versions" => CrateSelect::AllVersions,
+            invalid => return Err(CrateSelectParseError::InvalidKind(invalid.into())),
+        };
+
+        if payload.is_some() {
+            return Err(CrateSelectParseError::UnexpectedPayload);
+        }
+        Ok(select)
+    }
+}
 
 string_enum!(pub enum CapLints {
     Allow => "allow",
@@ -111,7 +284,9 @@ pub struct GitHubIssue {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Experiment {
     pub name: String,
-    pub toolchains: [Toolchain; 2],
+    /// The toolchains to test, in the order they're compared. Baseline experiments have a
+    /// single toolchain.
+    pub toolchains: Vec<Toolchain>,
     pub mode: Mode,
     pub cap_lints: CapLints,
     pub priority: i32,
@@ -124,6 +299,12 @@ pub struct Experiment {
     pub report_url: Option<String>,
     pub ignore_blacklist: bool,
     pub requirement: Option<String>,
+    /// The seed used to randomly select the crates, if any.
+    pub crates_seed: Option<u32>,
+    /// Whether crates are also built with all their features and without the default ones.
+    pub feature_matrix: bool,
+    /// The targets crates are also checked for, in addition to the host target.
+    pub targets: Vec<String>,
 }
 
 impl Experiment {
@@ -131,6 +312,42 @@ impl Experiment {
         Ok(db.exists("SELECT rowid FROM experiments WHERE name = ?1;", &[&name])?)
     }
 
+    /// Baseline experiments test a single toolchain, without comparing it against anything.
+    pub fn is_baseline(&self) -> bool {
+        self.toolchains.len() == 1
+    }
+
+    /// The toolchains to compare, once for each feature set and target tested by the experiment.
+    pub fn toolchain_sets(&self) -> Vec<Vec<Toolchain>> {
+        let mut sets = vec![self.toolchains.clone()];
+        if self.feature_matrix {
+            sets = FeatureSet::MATRIX
+                .iter()
+                .map(|&features| {
+                    self.toolchains
+                        .iter()
+                        .map(|tc| tc.with_features(features))
+                        .collect()
+                })
+                .collect();
+        }
+
+        let mut cross = Vec::new();
+        for target in &self.targets {
+            for set in &sets {
+                cross.push(set.iter().map(|tc| tc.with_target(target)).collect());
+            }
+        }
+        sets.append(&mut cross);
+
+        sets
+    }
+
+    /// Every toolchain results are recorded for, including the feature sets and the targets.
+    pub fn all_toolchains(&self) -> Vec<Toolchain> {
+        self.toolchain_sets().into_iter().flatten().collect()
+    }
+
     pub fn unfinished(db: &Database) -> Fallible<Vec<Experiment>> {
         let records = db.query(
             "SELECT * FROM experiments WHERE status != ?1 ORDER BY priority DESC, created_at;",
@@ -323,7 +540,7 @@ impl Experiment {
             )?
             .unwrap();
 
-        Ok((results_len, crates_len * 2))
+        Ok((results_len, crates_len * self.all_toolchains().len() as u32))
     }
 
     pub fn progress(&self, db: &Database) -> Fallible<u8> {
@@ -352,8 +569,8 @@ impl Experiment {
     pub fn get_uncompleted_crates(&self, db: &Database) -> Fallible<Vec<Crate>> {
         db.query(
             "SELECT crate FROM experiment_crates WHERE experiment = ?1
-            AND (SELECT COUNT(*) AS count FROM results WHERE results.experiment = ?1 AND results.crate = experiment_crates.crate) < 2;",
-            &[&self.name],
+            AND (SELECT COUNT(*) AS count FROM results WHERE results.experiment = ?1 AND results.crate = experiment_crates.crate) < ?2;",
+            &[&self.name, &(self.all_toolchains().len() as u32)],
             |r| {
                 let value: String = r.get("crate");
                 Ok(serde_json::from_str(&value)?)
@@ -368,8 +585,7 @@ struct ExperimentDBRecord {
     name: String,
     mode: String,
     cap_lints: String,
-    toolchain_start: String,
-    toolchain_end: String,
+    toolchains: String,
     priority: i32,
     created_at: DateTime<Utc>,
     started_at: Option<DateTime<Utc>>,
@@ -382,6 +598,9 @@ struct ExperimentDBRecord {
     report_url: Option<String>,
     ignore_blacklist: bool,
     requirement: Option<String>,
+    crates_seed: Option<u32>,
+    feature_matrix: bool,
+    targets: String,
 }
 
 impl ExperimentDBRecord {
@@ -390,8 +609,7 @@ impl ExperimentDBRecord {
             name: row.get("name"),
             mode: row.get("mode"),
             cap_lints: row.get("cap_lints"),
-            toolchain_start: row.get("toolchain_start"),
-            toolchain_end: row.get("toolchain_end"),
+            toolchains: row.get("toolchains"),
             priority: row.get("priority"),
             created_at: row.get("created_at"),
             started_at: row.get("started_at"),
@@ -404,13 +622,19 @@ impl ExperimentDBRecord {
             report_url: row.get("report_url"),
             ignore_blacklist: row.get("ignore_blacklist"),
             requirement: row.get("requirement"),
+            crates_seed: row.get("crates_seed"),
+            feature_matrix: row.get("feature_matrix"),
+            targets: row.get("targets"),
         }
     }
 
     fn into_experiment(self) -> Fallible<Experiment> {
         Ok(Experiment {
             name: self.name,
-            toolchains: [self.toolchain_start.parse()?, self.toolchain_end.parse()?],
+            toolchains: serde_json::from_str::<Vec<String>>(&self.toolchains)?
+                .iter()
+                .map(|tc| tc.parse::<Toolchain>())
+                .collect::<Result<_, _>>()?,
             cap_lints: self.cap_lints.parse()?,
             mode: self.mode.parse()?,
             priority: self.priority,
@@ -439,13 +663,18 @@ impl ExperimentDBRecord {
             report_url: self.report_url,
             ignore_blacklist: self.ignore_blacklist,
             requirement: self.requirement,
+            crates_seed: self.crates_seed,
+            feature_matrix: self.feature_matrix,
+            targets: serde_json::from_str(&self.targets)?,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Assignee, AssigneeParseError, Experiment, Status};
+    use super::{
+        Assignee, AssigneeParseError, CrateSelect, CrateSelectParseError, Experiment, Status,
+    };
     use crate::actions::{Action, ActionsCtx, CreateExperiment};
     use crate::agent::Capabilities;
     use crate::config::Config;
@@ -482,6 +711,91 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_crate_select_parsing() {
+        for select in &[
+            CrateSelect::Full,
+            CrateSelect::Demo,
+            CrateSelect::Random {
+                count: 20,
+                seed: None,
+            },
+            CrateSelect::Random {
+                count: 50,
+                seed: Some(42),
+            },
+            CrateSelect::Top(100),
+            CrateSelect::Percent(10),
+            CrateSelect::Local,
+            CrateSelect::Regressed("foo".into()),
+            CrateSelect::List(vec!["foo".into(), "bar-1.0.0".into()]),
+            CrateSelect::AltRegistry("internal".into()),
+            CrateSelect::AllVersions,
+            CrateSelect::Versions(vec!["syn".into(), "serde".into()]),
+        ] {
+            assert_eq!(&CrateSelect::from_str(&select.to_string()).unwrap(), select);
+        }
+        assert_eq!(
+            CrateSelect::Regressed("pr-1234".into())
+                .to_string()
+                .as_str(),
+            "regressed:pr-1234"
+        );
+
+        assert_eq!(
+            CrateSelect::from_str("list: foo, ,https://github.com/rust-lang/crater").unwrap(),
+            CrateSelect::List(vec![
+                "foo".into(),
+                "https://github.com/rust-lang/crater".into()
+            ])
+        );
+
+        for empty in &[
+            "",
+            "regressed",
+            "regressed:",
+            "list",
+            "list:",
+            "list:,",
+            "versions:",
+            "registry:",
+        ] {
+            let err = CrateSelect::from_str(empty).unwrap_err();
+            assert_eq!(err, CrateSelectParseError::Empty);
+        }
+
+        let err = CrateSelect::from_str("foo").unwrap_err();
+        assert_eq!(err, CrateSelectParseError::InvalidKind("foo".into()));
+
+        assert_eq!(
+            CrateSelect::from_str("small-random").unwrap(),
+            CrateSelect::Random {
+                count: 20,
+                seed: None
+            }
+        );
+        assert_eq!(
+            CrateSelect::from_str("top-100").unwrap(),
+            CrateSelect::Top(100)
+        );
+
+        let err = CrateSelect::from_str("full:foo").unwrap_err();
+        assert_eq!(err, CrateSelectParseError::UnexpectedPayload);
+
+        for (invalid, number) in &[
+            ("top-0", "0"),
+            ("top-foo", "foo"),
+            ("percent-101", "101"),
+            ("random-10:foo", "foo"),
+        ] {
+            let err = CrateSelect::from_str(invalid).unwrap_err();
+            assert_eq!(
+                err,
+                CrateSelectParseError::InvalidNumber(number.to_string())
+            );
+        }
+    }
+
     #[test]
     fn test_assigning_experiment() {
         let db = Database::temp().unwrap();
@@ -700,4 +1014,85 @@ mod tests {
         let uncompleted_crates = ex.get_uncompleted_crates(&db).unwrap();
         assert_eq!(uncompleted_crates.len(), crates.len() - 1);
     }
+
+    #[test]
+    fn test_feature_matrix() {
+        use crate::results::{DatabaseDB, EncodingType, TestResult, WriteResults};
+        use crate::toolchain::FeatureSet;
+
+        rustwide::logging::init();
+
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        CreateExperiment {
+            feature_matrix: true,
+            ..CreateExperiment::dummy("dummy")
+        }
+        .apply(&ctx)
+        .unwrap();
+        let ex = Experiment::get(&db, "dummy").unwrap().unwrap();
+        assert!(ex.feature_matrix);
+
+        // Every feature set compares the toolchains of the experiment
+        let sets = ex.toolchain_sets();
+        assert_eq!(sets.len(), FeatureSet::MATRIX.len());
+        for (toolchains, &features) in sets.iter().zip(FeatureSet::MATRIX) {
+            assert_eq!(toolchains.len(), ex.toolchains.len());
+            assert!(toolchains.iter().all(|tc| tc.features == features));
+        }
+        assert_eq!(ex.all_toolchains().len(), 6);
+
+        // Crates are only completed once they were built with all the feature sets
+        let crates = ex.get_uncompleted_crates(&db).unwrap();
+        let results = DatabaseDB::new(&db);
+        for tc in &ex.all_toolchains() {
+            assert!(ex.get_uncompleted_crates(&db).unwrap().contains(&crates[0]));
+
+            results
+                .record_result(
+                    &ex,
+                    tc,
+                    &crates[0],
+                    None,
+                    &config,
+                    EncodingType::Plain,
+                    || Ok(TestResult::TestPass),
+                )
+                .unwrap();
+        }
+        assert!(!ex.get_uncompleted_crates(&db).unwrap().contains(&crates[0]));
+    }
+
+    #[test]
+    fn test_targets() {
+        rustwide::logging::init();
+
+        let db = Database::temp().unwrap();
+        let config = Config::default();
+        let ctx = ActionsCtx::new(&db, &config);
+
+        crate::crates::lists::setup_test_lists(&db, &config).unwrap();
+
+        CreateExperiment {
+            targets: vec!["wasm32-unknown-unknown".into()],
+            ..CreateExperiment::dummy("dummy")
+        }
+        .apply(&ctx)
+        .unwrap();
+        let ex = Experiment::get(&db, "dummy").unwrap().unwrap();
+        assert_eq!(ex.targets, vec!["wasm32-unknown-unknown".to_string()]);
+
+        // The host target is always tested, followed by the additional targets
+        let sets = ex.toolchain_sets();
+        assert_eq!(sets.len(), 2);
+        assert!(sets[0].iter().all(|tc| tc.target.is_none()));
+        assert!(sets[1]
+            .iter()
+            .all(|tc| tc.target.as_ref().map(|t| t.as_str()) == Some("wasm32-unknown-unknown")));
+        assert_eq!(ex.all_toolchains().len(), 4);
+    }
 }
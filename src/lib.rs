@@ -9,6 +9,7 @@
 pub mod actions;
 pub mod agent;
 mod assets;
+pub mod bisect;
 #[macro_use]
 pub mod utils;
 pub mod config;
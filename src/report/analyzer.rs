@@ -0,0 +1,263 @@
+use crate::crates::{Crate, DependencyGraph};
+use crate::experiments::Experiment;
+use crate::prelude::*;
+use crate::results::{ReadResults, TestResult};
+use crate::toolchain::Toolchain;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+lazy_static! {
+    static ref ERROR_CODE_RE: Regex = Regex::new(r"error\[(E\d{4})\]").unwrap();
+    static ref TEST_PANIC_RE: Regex = Regex::new(r"thread '[^']*' panicked at").unwrap();
+    static ref COMPILE_FAIL_RE: Regex =
+        Regex::new(r"(?i)error: could not compile `([^`]+)`").unwrap();
+}
+
+/// The likely root cause of a failure, extracted from its log.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub enum RootCause {
+    ICE,
+    Linker,
+    TestPanic,
+    ErrorCode(String),
+}
+
+impl fmt::Display for RootCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RootCause::ICE => write!(f, "ice"),
+            RootCause::Linker => write!(f, "linker"),
+            RootCause::TestPanic => write!(f, "test-panic"),
+            RootCause::ErrorCode(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+impl FromStr for RootCause {
+    type Err = ::failure::Error;
+
+    fn from_str(input: &str) -> Fallible<Self> {
+        Ok(match input {
+            "ice" => RootCause::ICE,
+            "linker" => RootCause::Linker,
+            "test-panic" => RootCause::TestPanic,
+            code if code.len() == 5
+                && code.starts_with('E')
+                && code[1..].chars().all(|c| c.is_ascii_digit()) =>
+            {
+                RootCause::ErrorCode(code.into())
+            }
+            other => bail!("unknown root cause: {}", other),
+        })
+    }
+}
+
+impl_serde_from_parse!(RootCause, expecting = "a root cause");
+
+/// Scan a failing log for rustc error codes, ICEs, linker errors and test panics.
+pub(super) fn classify_log(log: &str, result: TestResult) -> Vec<RootCause> {
+    let mut causes = BTreeSet::new();
+
+    for captures in ERROR_CODE_RE.captures_iter(log) {
+        causes.insert(RootCause::ErrorCode(captures[1].to_string()));
+    }
+    if log.contains("internal compiler error") || log.contains("compiler unexpectedly panicked") {
+        causes.insert(RootCause::ICE);
+    }
+    if log.contains("error: linking with `") || log.contains("error: linker `") {
+        causes.insert(RootCause::Linker);
+    }
+    // Build scripts can panic too, so panics are only relevant when the tests failed
+    if let TestResult::TestFail(_) = result {
+        if TEST_PANIC_RE.is_match(log) {
+            causes.insert(RootCause::TestPanic);
+        }
+    }
+
+    causes.into_iter().collect()
+}
+
+/// Find the dependency that caused the build of a crate to fail, if the failure didn't happen in
+/// the crate itself.
+pub(super) fn find_root_crate(log: &str, krate: &Crate, deps: &DependencyGraph) -> Option<String> {
+    let name = match krate {
+        Crate::Registry(details) => &details.name,
+        Crate::GitHub(repo) => &repo.name,
+        Crate::Local(name) => name,
+        Crate::AltRegistry(details) => &details.name,
+        Crate::Git(repo) => repo.name(),
+    };
+
+    let mut failed = Vec::new();
+    for captures in COMPILE_FAIL_RE.captures_iter(log) {
+        let failed_crate = &captures[1];
+        if failed_crate == name || failed.iter().any(|f| f == failed_crate) {
+            continue;
+        }
+
+        // Only crates.io crates are tracked in the dependency graph, so the dependencies of
+        // other crates can't be checked
+        let is_dependency = match krate {
+            Crate::Registry(details) => deps.depends_on(&details.name, failed_crate),
+            Crate::GitHub(_) | Crate::Local(_) | Crate::AltRegistry(_) | Crate::Git(_) => {
+                deps.contains(failed_crate)
+            }
+        };
+        if is_dependency {
+            failed.push(failed_crate.to_string());
+        }
+    }
+
+    // If multiple dependencies failed the root is the one all the others depend on
+    let root = failed
+        .iter()
+        .find(|root| {
+            failed
+                .iter()
+                .all(|other| other == *root || deps.depends_on(other, root))
+        })
+        .or_else(|| failed.first());
+    root.cloned()
+}
+
+#[derive(Default)]
+pub(super) struct LogAnalysis {
+    pub(super) causes: Vec<RootCause>,
+    pub(super) root_crate: Option<String>,
+}
+
+/// Load the log of a failed run and find the root causes of the failure.
+pub(super) fn analyze<DB: ReadResults>(
+    db: &DB,
+    ex: &Experiment,
+    toolchain: &Toolchain,
+    krate: &Crate,
+    result: TestResult,
+    deps: &DependencyGraph,
+) -> Fallible<LogAnalysis> {
+    match result {
+        TestResult::BuildFail(_) | TestResult::TestFail(_) => {}
+        _ => return Ok(LogAnalysis::default()),
+    }
+
+    if let Some(log) = db.load_log(ex, toolchain, krate)? {
+        let log = log.to_plain()?;
+        let log = String::from_utf8_lossy(&log);
+        Ok(LogAnalysis {
+            causes: classify_log(&log, result),
+            root_crate: find_root_crate(&log, krate, deps),
+        })
+    } else {
+        Ok(LogAnalysis::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_log, find_root_crate, RootCause};
+    use crate::crates::{Crate, DependencyGraph, RegistryCrate};
+    use crate::results::{FailureReason, TestResult};
+
+    const BUILD_FAIL: TestResult = TestResult::BuildFail(FailureReason::Unknown);
+    const TEST_FAIL: TestResult = TestResult::TestFail(FailureReason::Unknown);
+
+    #[test]
+    fn test_classify_log() {
+        macro_rules! test_classify {
+            ($log:expr, $result:expr, [$($cause:expr),*]) => {
+                assert_eq!(classify_log($log, $result), vec![$($cause),*]);
+            };
+        }
+
+        test_classify!("error: expected one of `!` or `::`", BUILD_FAIL, []);
+        test_classify!(
+            "error[E0599]: no method named `foo`\nerror[E0308]: mismatched types\n\
+             error[E0308]: mismatched types",
+            BUILD_FAIL,
+            [
+                RootCause::ErrorCode("E0308".into()),
+                RootCause::ErrorCode("E0599".into())
+            ]
+        );
+        test_classify!(
+            "error: internal compiler error: src/librustc/ty/mod.rs:1234: oops",
+            BUILD_FAIL,
+            [RootCause::ICE]
+        );
+        test_classify!(
+            "error: linking with `cc` failed: exit code: 1",
+            BUILD_FAIL,
+            [RootCause::Linker]
+        );
+        test_classify!(
+            "thread 'bad_test' panicked at 'this crate is just broken', src/main.rs:7:5",
+            TEST_FAIL,
+            [RootCause::TestPanic]
+        );
+        test_classify!(
+            "thread 'main' panicked at 'build script failed', build.rs:3:5",
+            BUILD_FAIL,
+            []
+        );
+    }
+
+    #[test]
+    fn test_root_cause_parse() {
+        for cause in &[
+            RootCause::ICE,
+            RootCause::Linker,
+            RootCause::TestPanic,
+            RootCause::ErrorCode("E0308".into()),
+        ] {
+            assert_eq!(&cause.to_string().parse::<RootCause>().unwrap(), cause);
+        }
+
+        assert!("E03a8".parse::<RootCause>().is_err());
+        assert!("foo".parse::<RootCause>().is_err());
+    }
+
+    #[test]
+    fn test_find_root_crate() {
+        let deps = vec![("foo", "bar"), ("bar", "baz"), ("bar", "libc")]
+            .into_iter()
+            .map(|(krate, dep)| (krate.to_string(), dep.to_string()))
+            .collect::<DependencyGraph>();
+        let foo = Crate::Registry(RegistryCrate {
+            name: "foo".into(),
+            version: "1.0.0".into(),
+        });
+        let local = Crate::Local("foo".into());
+
+        // Failures in the crate itself don't have a root crate
+        assert_eq!(
+            find_root_crate("error: Could not compile `foo`.", &foo, &deps),
+            None
+        );
+        assert_eq!(
+            find_root_crate("error: could not compile `baz`", &foo, &deps),
+            Some("baz".into())
+        );
+        assert_eq!(
+            find_root_crate("error: could not compile `baz`", &local, &deps),
+            Some("baz".into())
+        );
+
+        // Crates that are not dependencies are ignored
+        assert_eq!(
+            find_root_crate("error: could not compile `quux`", &foo, &deps),
+            None
+        );
+
+        // The deepest failed dependency is the root
+        assert_eq!(
+            find_root_crate(
+                "error: could not compile `bar`\nerror: could not compile `baz`",
+                &foo,
+                &deps
+            ),
+            Some("baz".into())
+        );
+    }
+}
@@ -2,7 +2,7 @@ use crate::config::Config;
 use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::report::{compare, ReportWriter};
+use crate::report::{categorize, ReportWriter};
 use crate::results::{EncodedLog, EncodingType, ReadResults};
 use flate2::{write::GzEncoder, Compression};
 use std::collections::HashMap;
@@ -30,46 +30,50 @@ pub fn write_logs_archives<DB: ReadResults, W: ReportWriter>(
             continue;
         }
 
-        let res1 = db.load_test_result(ex, &ex.toolchains[0], krate)?;
-        let res2 = db.load_test_result(ex, &ex.toolchains[1], krate)?;
-        let comparison = compare(config, krate, res1, res2);
-
-        for tc in &ex.toolchains {
-            let log = db
-                .load_log(ex, tc, krate)
-                .and_then(|c| c.ok_or_else(|| err_msg("missing logs")))
-                .with_context(|_| format!("failed to read log of {} on {}", krate, tc));
-
-            let log_bytes: EncodedLog = match log {
-                Ok(l) => l,
-                Err(e) => {
-                    crate::utils::report_failure(&e);
-                    continue;
-                }
-            };
-
-            let log_bytes = log_bytes.to_plain()?;
-            let log_bytes = log_bytes.as_slice();
-
-            let path = format!(
-                "{}/{}/{}.txt",
-                comparison,
-                krate.id(),
-                tc.to_path_component(),
-            );
-
-            let mut header = TarHeader::new_gnu();
-            header.set_size(log_bytes.len() as u64);
-            header.set_mode(0o644);
-            header.set_cksum();
-
-            all.append_data(&mut header, &path, log_bytes)?;
-            by_comparison
-                .entry(comparison)
-                .or_insert_with(|| {
-                    TarBuilder::new(GzEncoder::new(Vec::new(), Compression::default()))
-                })
-                .append_data(&mut header, &path, log_bytes)?;
+        for toolchains in ex.toolchain_sets() {
+            let results = toolchains
+                .iter()
+                .map(|tc| db.load_test_result(ex, tc, krate))
+                .collect::<Fallible<Vec<_>>>()?;
+            let (comparison, _) = categorize(config, krate, &results);
+
+            for tc in &toolchains {
+                let log = db
+                    .load_log(ex, tc, krate)
+                    .and_then(|c| c.ok_or_else(|| err_msg("missing logs")))
+                    .with_context(|_| format!("failed to read log of {} on {}", krate, tc));
+
+                let log_bytes: EncodedLog = match log {
+                    Ok(l) => l,
+                    Err(e) => {
+                        crate::utils::report_failure(&e);
+                        continue;
+                    }
+                };
+
+                let log_bytes = log_bytes.to_plain()?;
+                let log_bytes = log_bytes.as_slice();
+
+                let path = format!(
+                    "{}/{}/{}.txt",
+                    comparison,
+                    krate.id(),
+                    tc.to_path_component(),
+                );
+
+                let mut header = TarHeader::new_gnu();
+                header.set_size(log_bytes.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+
+                all.append_data(&mut header, &path, log_bytes)?;
+                by_comparison
+                    .entry(comparison)
+                    .or_insert_with(|| {
+                        TarBuilder::new(GzEncoder::new(Vec::new(), Compression::default()))
+                    })
+                    .append_data(&mut header, &path, log_bytes)?;
+            }
         }
     }
 
@@ -0,0 +1,144 @@
+use crate::report::{BuildTestResult, TestResults};
+use crate::results::DiagnosticLevel;
+use crate::toolchain::FeatureSet;
+use std::collections::HashMap;
+
+/// A crate emitting a lint or an error more often with the second toolchain of the experiment.
+#[derive(Serialize, Clone)]
+pub struct DiagnosticCrate {
+    name: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    features: Option<FeatureSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    /// How many times the diagnostic was emitted with the two toolchains.
+    counts: [u32; 2],
+    log: String,
+}
+
+/// A lint or an error introduced by the second toolchain of the experiment.
+#[derive(Serialize)]
+pub struct NewDiagnostic {
+    pub code: String,
+    pub level: DiagnosticLevel,
+    pub crates: Vec<DiagnosticCrate>,
+}
+
+fn counts(run: &BuildTestResult) -> HashMap<(&str, DiagnosticLevel), u32> {
+    run.diagnostics
+        .iter()
+        .map(|d| ((d.code.as_str(), d.level), d.count))
+        .collect()
+}
+
+/// Find the diagnostics emitted more often with the second toolchain than with the first one,
+/// grouped by lint name or error code. The diagnostics introduced in the most crates come first.
+pub fn new_diagnostics(res: &TestResults) -> Vec<NewDiagnostic> {
+    let mut diagnostics: HashMap<(&str, DiagnosticLevel), Vec<DiagnosticCrate>> = HashMap::new();
+
+    for krate in &res.crates {
+        let (before, after) = match (krate.runs.get(0), krate.runs.get(1)) {
+            (Some(Some(before)), Some(Some(after))) => (before, after),
+            _ => continue,
+        };
+
+        let counts_before = counts(before);
+        for (key, count) in counts(after) {
+            let count_before = counts_before.get(&key).cloned().unwrap_or(0);
+            if count <= count_before {
+                continue;
+            }
+
+            diagnostics
+                .entry(key)
+                .or_insert_with(Vec::new)
+                .push(DiagnosticCrate {
+                    name: krate.name.clone(),
+                    url: krate.url.clone(),
+                    features: krate.features,
+                    target: krate.target.clone(),
+                    counts: [count_before, count],
+                    log: after.log.clone(),
+                });
+        }
+    }
+
+    let mut diagnostics = diagnostics
+        .into_iter()
+        .map(|((code, level), crates)| NewDiagnostic {
+            code: code.to_string(),
+            level,
+            crates,
+        })
+        .collect::<Vec<_>>();
+    diagnostics.sort_by(|a, b| {
+        b.crates
+            .len()
+            .cmp(&a.crates.len())
+            .then_with(|| a.code.cmp(&b.code))
+            .then_with(|| a.level.to_str().cmp(b.level.to_str()))
+    });
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_diagnostics;
+    use crate::report::{BuildTestResult, CrateResult, TestResults};
+    use crate::results::{DiagnosticCount, DiagnosticLevel, TestResult};
+
+    fn run(diagnostics: &[(&str, DiagnosticLevel, u32)]) -> Option<BuildTestResult> {
+        Some(BuildTestResult {
+            diagnostics: diagnostics
+                .iter()
+                .map(|&(code, level, count)| DiagnosticCount {
+                    code: code.into(),
+                    level,
+                    count,
+                })
+                .collect(),
+            ..BuildTestResult::dummy(TestResult::TestPass)
+        })
+    }
+
+    #[test]
+    fn test_new_diagnostics() {
+        let warning = DiagnosticLevel::Warning;
+        let res = TestResults {
+            crates: vec![
+                CrateResult::dummy(
+                    "a",
+                    vec![
+                        run(&[("unused_variables", warning, 1)]),
+                        run(&[("unused_variables", warning, 1), ("deprecated", warning, 2)]),
+                    ],
+                ),
+                CrateResult::dummy(
+                    "b",
+                    vec![
+                        run(&[("unused_variables", warning, 3)]),
+                        run(&[("unused_variables", warning, 1), ("deprecated", warning, 1)]),
+                    ],
+                ),
+                CrateResult::dummy(
+                    "c",
+                    vec![run(&[]), run(&[("unused_variables", warning, 1)])],
+                ),
+                // Crates without results for both toolchains can't be compared
+                CrateResult::dummy("d", vec![None, run(&[("dead_code", warning, 1)])]),
+            ],
+        };
+
+        let diagnostics = new_diagnostics(&res);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.code.as_str(), d.crates.len()))
+                .collect::<Vec<_>>(),
+            vec![("deprecated", 2), ("unused_variables", 1)]
+        );
+        assert_eq!(diagnostics[0].crates[0].counts, [0, 2]);
+        assert_eq!(diagnostics[1].crates[0].name, "c");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::report::TestResults;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A crate that would break if a future-incompatibility lint became a hard error.
+#[derive(Serialize)]
+pub struct FutureIncompatCrate {
+    name: String,
+    url: String,
+    /// The packages triggering the lint with any feature set or target, either the crate itself
+    /// or its dependencies.
+    packages: BTreeSet<String>,
+    /// The log of the first feature set or target triggering the lint.
+    log: String,
+}
+
+/// A lint that will become a hard error in a future release, with the crates triggering it.
+#[derive(Serialize)]
+pub struct FutureIncompatLint {
+    pub lint: String,
+    pub crates: Vec<FutureIncompatCrate>,
+}
+
+/// Group the crates by the future-incompatibility lints they trigger with the last toolchain of
+/// the experiment, which is the closest to the release turning the lints into hard errors. The
+/// lints breaking the most crates come first. Crates tested with multiple feature sets or targets
+/// are only counted once.
+pub fn future_incompat_lints(res: &TestResults) -> Vec<FutureIncompatLint> {
+    let mut lints: BTreeMap<&str, Vec<FutureIncompatCrate>> = BTreeMap::new();
+    // The position of each crate in the list of each lint
+    let mut positions: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for krate in &res.crates {
+        let run = match krate.runs.last() {
+            Some(Some(run)) => run,
+            _ => continue,
+        };
+
+        let mut packages: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for future_incompat in &run.future_incompats {
+            packages
+                .entry(future_incompat.lint.as_str())
+                .or_insert_with(BTreeSet::new)
+                .insert(future_incompat.package.clone());
+        }
+
+        for (lint, mut packages) in packages {
+            let crates = lints.entry(lint).or_insert_with(Vec::new);
+            match positions.get(&(lint, krate.name.as_str())) {
+                Some(&i) => crates[i].packages.append(&mut packages),
+                None => {
+                    positions.insert((lint, krate.name.as_str()), crates.len());
+                    crates.push(FutureIncompatCrate {
+                        name: krate.name.clone(),
+                        url: krate.url.clone(),
+                        packages,
+                        log: run.log.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut lints = lints
+        .into_iter()
+        .map(|(lint, crates)| FutureIncompatLint {
+            lint: lint.to_string(),
+            crates,
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so lints breaking the same number of crates stay sorted by name
+    lints.sort_by(|a, b| b.crates.len().cmp(&a.crates.len()));
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::future_incompat_lints;
+    use crate::report::{BuildTestResult, CrateResult, TestResults};
+    use crate::results::{FutureIncompat, TestResult};
+    use crate::toolchain::FeatureSet;
+
+    fn run(future_incompats: &[(&str, &str)]) -> Option<BuildTestResult> {
+        Some(BuildTestResult {
+            future_incompats: future_incompats
+                .iter()
+                .map(|&(lint, package)| FutureIncompat {
+                    lint: lint.into(),
+                    package: package.into(),
+                })
+                .collect(),
+            ..BuildTestResult::dummy(TestResult::TestPass)
+        })
+    }
+
+    #[test]
+    fn test_future_incompat_lints() {
+        let res = TestResults {
+            crates: vec![
+                CrateResult::dummy(
+                    "a",
+                    vec![
+                        run(&[]),
+                        run(&[
+                            ("semicolon_in_expressions_from_macros", "a v1.0.0"),
+                            ("semicolon_in_expressions_from_macros", "b v0.1.0"),
+                        ]),
+                    ],
+                ),
+                CrateResult::dummy(
+                    "c",
+                    vec![
+                        run(&[]),
+                        run(&[
+                            ("semicolon_in_expressions_from_macros", "b v0.1.0"),
+                            ("proc_macro_back_compat", "rental v0.5.5"),
+                        ]),
+                    ],
+                ),
+                // Crates are counted once across feature sets
+                CrateResult {
+                    features: Some(FeatureSet::AllFeatures),
+                    ..CrateResult::dummy(
+                        "a",
+                        vec![
+                            run(&[]),
+                            run(&[("semicolon_in_expressions_from_macros", "c v0.2.0")]),
+                        ],
+                    )
+                },
+                // Only the last toolchain is considered
+                CrateResult::dummy(
+                    "d",
+                    vec![run(&[("where_clauses_object_safety", "d v1.0.0")]), None],
+                ),
+            ],
+        };
+
+        let lints = future_incompat_lints(&res);
+        assert_eq!(
+            lints
+                .iter()
+                .map(|l| (l.lint.as_str(), l.crates.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("semicolon_in_expressions_from_macros", 2),
+                ("proc_macro_back_compat", 1),
+            ]
+        );
+        assert_eq!(lints[0].crates[0].packages.len(), 3);
+    }
+}
@@ -1,11 +1,19 @@
 use crate::assets;
-use crate::experiments::Experiment;
+use crate::crates::DependencyGraph;
+use crate::experiments::{Experiment, Mode};
 use crate::prelude::*;
-use crate::report::{archives::Archive, Comparison, CrateResult, ReportWriter, TestResults};
+use crate::report::{
+    archives::Archive,
+    diagnostics::{self, NewDiagnostic},
+    future_incompat::{self, FutureIncompatLint},
+    perf::{self, PerfComparison, PerfResult},
+    unstable_features::{self, FeatureUsage},
+    Comparison, CrateResult, ReportWriter, RootCause, TestResults,
+};
 use crate::results::{BrokenReason, EncodingType, FailureReason, TestResult};
 use mime;
 use minifier;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize)]
 enum Color {
@@ -48,6 +56,17 @@ impl ResultColor for TestResult {
     }
 }
 
+impl ResultColor for PerfComparison {
+    fn color(&self) -> Color {
+        match self {
+            PerfComparison::Regressed => Color::Single("#db3026"),
+            PerfComparison::Improved => Color::Single("#5630db"),
+            PerfComparison::Unchanged => Color::Single("#72a156"),
+            PerfComparison::Unknown => Color::Single("#494b4a"),
+        }
+    }
+}
+
 trait ResultName {
     fn name(&self) -> String;
 }
@@ -58,6 +77,8 @@ impl ResultName for FailureReason {
             FailureReason::Unknown => "failed".into(),
             FailureReason::Timeout => "timed out".into(),
             FailureReason::OOM => "OOM".into(),
+            FailureReason::UndefinedBehavior => "UB".into(),
+            FailureReason::OutOfTime => "out of time".into(),
         }
     }
 }
@@ -85,6 +106,17 @@ impl ResultName for TestResult {
     }
 }
 
+impl ResultName for RootCause {
+    fn name(&self) -> String {
+        match self {
+            RootCause::ICE => "internal compiler error".into(),
+            RootCause::Linker => "linker error".into(),
+            RootCause::TestPanic => "test panic".into(),
+            RootCause::ErrorCode(code) => format!("error[{}]", code),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct NavbarItem {
     label: &'static str,
@@ -96,12 +128,18 @@ struct NavbarItem {
 enum CurrentPage {
     Summary,
     Full,
+    Causes,
+    Roots,
+    Perf,
+    Diagnostics,
+    FutureIncompat,
+    UnstableFeatures,
     Downloads,
 }
 
 impl CurrentPage {
-    fn navbar(&self) -> Vec<NavbarItem> {
-        vec![
+    fn navbar(&self, ex: &Experiment) -> Vec<NavbarItem> {
+        let mut items = vec![
             NavbarItem {
                 label: "Summary",
                 url: "index.html",
@@ -113,11 +151,48 @@ impl CurrentPage {
                 active: *self == CurrentPage::Full,
             },
             NavbarItem {
-                label: "Downloads",
-                url: "downloads.html",
-                active: *self == CurrentPage::Downloads,
+                label: "Regressions by cause",
+                url: "causes.html",
+                active: *self == CurrentPage::Causes,
             },
-        ]
+            NavbarItem {
+                label: "Root regressions",
+                url: "roots.html",
+                active: *self == CurrentPage::Roots,
+            },
+        ];
+        // The pages comparing two toolchains are only generated when there's a second one
+        if ex.toolchains.len() > 1 {
+            items.push(NavbarItem {
+                label: "Compile times",
+                url: "perf.html",
+                active: *self == CurrentPage::Perf,
+            });
+            items.push(NavbarItem {
+                label: "New diagnostics",
+                url: "diagnostics.html",
+                active: *self == CurrentPage::Diagnostics,
+            });
+        }
+        items.push(NavbarItem {
+            label: "Future incompatibilities",
+            url: "future-incompat.html",
+            active: *self == CurrentPage::FutureIncompat,
+        });
+        // The unstable features page is only generated for the experiments looking for them
+        if ex.mode == Mode::UnstableFeatures {
+            items.push(NavbarItem {
+                label: "Unstable features",
+                url: "unstable-features.html",
+                active: *self == CurrentPage::UnstableFeatures,
+            });
+        }
+        items.push(NavbarItem {
+            label: "Downloads",
+            url: "downloads.html",
+            active: *self == CurrentPage::Downloads,
+        });
+        items
     }
 }
 
@@ -134,6 +209,74 @@ struct ResultsContext<'a> {
     result_names: HashMap<TestResult, String>,
 }
 
+#[derive(Serialize)]
+struct CauseCategory {
+    id: String,
+    name: String,
+    crates: Vec<CrateResult>,
+}
+
+#[derive(Serialize)]
+struct CausesContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    causes: Vec<CauseCategory>,
+    crates_count: usize,
+
+    result_names: HashMap<TestResult, String>,
+}
+
+#[derive(Serialize)]
+struct RootCategory {
+    name: String,
+    dependents_count: usize,
+    crates: Vec<CrateResult>,
+}
+
+#[derive(Serialize)]
+struct RootsContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    roots: Vec<RootCategory>,
+    crates_count: usize,
+
+    result_names: HashMap<TestResult, String>,
+}
+
+#[derive(Serialize)]
+struct PerfContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    categories: HashMap<PerfComparison, Vec<PerfResult>>,
+    crates_count: usize,
+
+    comparison_colors: HashMap<PerfComparison, Color>,
+}
+
+#[derive(Serialize)]
+struct DiagnosticsContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    diagnostics: Vec<NewDiagnostic>,
+    crates_count: usize,
+}
+
+#[derive(Serialize)]
+struct FutureIncompatContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    lints: Vec<FutureIncompatLint>,
+    crates_count: usize,
+}
+
+#[derive(Serialize)]
+struct UnstableFeaturesContext<'a> {
+    ex: &'a Experiment,
+    nav: Vec<NavbarItem>,
+    features: Vec<FeatureUsage>,
+    crates_count: usize,
+}
+
 #[derive(Serialize)]
 struct DownloadsContext<'a> {
     ex: &'a Experiment,
@@ -158,7 +301,12 @@ fn write_report<W: ReportWriter>(
     let mut categories = HashMap::new();
     for result in &res.crates {
         // Skip some categories if this is not the full report
-        if !full && !result.res.show_in_summary() {
+        let show_in_summary = if ex.is_baseline() {
+            result.res.show_in_baseline_summary()
+        } else {
+            result.res.show_in_summary()
+        };
+        if !full && !show_in_summary {
             continue;
         }
 
@@ -166,15 +314,7 @@ fn write_report<W: ReportWriter>(
         comparison_colors
             .entry(result.res)
             .or_insert_with(|| result.res.color());
-        if let Some(ref run) = result.runs[0] {
-            result_colors
-                .entry(run.res)
-                .or_insert_with(|| run.res.color());
-            result_names
-                .entry(run.res)
-                .or_insert_with(|| run.res.name());
-        }
-        if let Some(ref run) = result.runs[1] {
+        for run in result.runs.iter().filter_map(Option::as_ref) {
             result_colors
                 .entry(run.res)
                 .or_insert_with(|| run.res.color());
@@ -194,7 +334,7 @@ fn write_report<W: ReportWriter>(
         } else {
             CurrentPage::Summary
         }
-        .navbar(),
+        .navbar(ex),
         categories,
         full,
         crates_count,
@@ -210,6 +350,234 @@ fn write_report<W: ReportWriter>(
     Ok(())
 }
 
+fn write_causes<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    dest: &W,
+) -> Fallible<()> {
+    let mut result_names = HashMap::new();
+    let mut causes = BTreeMap::new();
+    let mut unclassified = Vec::new();
+
+    for result in &res.crates {
+        if result.res != Comparison::Regressed {
+            continue;
+        }
+
+        for run in result.runs.iter().filter_map(Option::as_ref) {
+            result_names
+                .entry(run.res)
+                .or_insert_with(|| run.res.name());
+        }
+
+        if result.causes.is_empty() {
+            unclassified.push(result.clone());
+        }
+        for cause in &result.causes {
+            causes
+                .entry(cause.clone())
+                .or_insert_with(Vec::new)
+                .push(result.clone());
+        }
+    }
+
+    let mut causes = causes
+        .into_iter()
+        .map(|(cause, crates)| CauseCategory {
+            id: cause.to_string(),
+            name: cause.name(),
+            crates,
+        })
+        .collect::<Vec<_>>();
+    if !unclassified.is_empty() {
+        causes.push(CauseCategory {
+            id: "unclassified".into(),
+            name: "unclassified".into(),
+            crates: unclassified,
+        });
+    }
+
+    let context = CausesContext {
+        ex,
+        nav: CurrentPage::Causes.navbar(ex),
+        causes,
+        crates_count,
+        result_names,
+    };
+
+    info!("generating causes.html");
+    let html = minifier::html::minify(&assets::render_template("report/causes.html", &context)?);
+    dest.write_string("causes.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
+fn write_roots<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    deps: &DependencyGraph,
+    dest: &W,
+) -> Fallible<()> {
+    let mut result_names = HashMap::new();
+    let mut roots = HashMap::new();
+
+    for result in &res.crates {
+        let root = match (result.res, &result.root_crate) {
+            (Comparison::Regressed, Some(root)) => root,
+            _ => continue,
+        };
+
+        for run in result.runs.iter().filter_map(Option::as_ref) {
+            result_names
+                .entry(run.res)
+                .or_insert_with(|| run.res.name());
+        }
+
+        roots
+            .entry(root.clone())
+            .or_insert_with(Vec::new)
+            .push(result.clone());
+    }
+
+    // Show the root crates breaking the most dependents first
+    let mut roots = roots
+        .into_iter()
+        .map(|(name, crates)| RootCategory {
+            dependents_count: deps.dependents_count(&name),
+            name,
+            crates,
+        })
+        .collect::<Vec<_>>();
+    roots.sort_by(|a, b| {
+        b.crates
+            .len()
+            .cmp(&a.crates.len())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    let context = RootsContext {
+        ex,
+        nav: CurrentPage::Roots.navbar(ex),
+        roots,
+        crates_count,
+        result_names,
+    };
+
+    info!("generating roots.html");
+    let html = minifier::html::minify(&assets::render_template("report/roots.html", &context)?);
+    dest.write_string("roots.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
+fn write_perf<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    dest: &W,
+) -> Fallible<()> {
+    let mut comparison_colors = HashMap::new();
+    let mut categories = HashMap::new();
+    for result in perf::compare_perf(res) {
+        if result.res == PerfComparison::Unknown {
+            continue;
+        }
+
+        comparison_colors
+            .entry(result.res)
+            .or_insert_with(|| result.res.color());
+        categories
+            .entry(result.res)
+            .or_insert_with(Vec::new)
+            .push(result);
+    }
+
+    let context = PerfContext {
+        ex,
+        nav: CurrentPage::Perf.navbar(ex),
+        categories,
+        crates_count,
+        comparison_colors,
+    };
+
+    info!("generating perf.html");
+    let html = minifier::html::minify(&assets::render_template("report/perf.html", &context)?);
+    dest.write_string("perf.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
+fn write_diagnostics<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    dest: &W,
+) -> Fallible<()> {
+    let context = DiagnosticsContext {
+        ex,
+        nav: CurrentPage::Diagnostics.navbar(ex),
+        diagnostics: diagnostics::new_diagnostics(res),
+        crates_count,
+    };
+
+    info!("generating diagnostics.html");
+    let html = minifier::html::minify(&assets::render_template(
+        "report/diagnostics.html",
+        &context,
+    )?);
+    dest.write_string("diagnostics.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
+fn write_future_incompat<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    dest: &W,
+) -> Fallible<()> {
+    let context = FutureIncompatContext {
+        ex,
+        nav: CurrentPage::FutureIncompat.navbar(ex),
+        lints: future_incompat::future_incompat_lints(res),
+        crates_count,
+    };
+
+    info!("generating future-incompat.html");
+    let html = minifier::html::minify(&assets::render_template(
+        "report/future-incompat.html",
+        &context,
+    )?);
+    dest.write_string("future-incompat.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
+fn write_unstable_features<W: ReportWriter>(
+    ex: &Experiment,
+    crates_count: usize,
+    res: &TestResults,
+    dest: &W,
+) -> Fallible<()> {
+    let context = UnstableFeaturesContext {
+        ex,
+        nav: CurrentPage::UnstableFeatures.navbar(ex),
+        features: unstable_features::unstable_features(res),
+        crates_count,
+    };
+
+    info!("generating unstable-features.html");
+    let html = minifier::html::minify(&assets::render_template(
+        "report/unstable-features.html",
+        &context,
+    )?);
+    dest.write_string("unstable-features.html", html.into(), &mime::TEXT_HTML)?;
+
+    Ok(())
+}
+
 fn write_downloads<W: ReportWriter>(
     ex: &Experiment,
     crates_count: usize,
@@ -218,7 +586,7 @@ fn write_downloads<W: ReportWriter>(
 ) -> Fallible<()> {
     let context = DownloadsContext {
         ex,
-        nav: CurrentPage::Downloads.navbar(),
+        nav: CurrentPage::Downloads.navbar(ex),
         crates_count,
         available_archives,
     };
@@ -235,12 +603,23 @@ pub fn write_html_report<W: ReportWriter>(
     crates_count: usize,
     res: &TestResults,
     available_archives: Vec<Archive>,
+    deps: &DependencyGraph,
     dest: &W,
 ) -> Fallible<()> {
     let js_in = assets::load("report.js")?;
     let css_in = assets::load("report.css")?;
     write_report(ex, crates_count, res, false, "index.html", dest)?;
     write_report(ex, crates_count, res, true, "full.html", dest)?;
+    write_causes(ex, crates_count, res, dest)?;
+    write_roots(ex, crates_count, res, deps, dest)?;
+    if ex.toolchains.len() > 1 {
+        write_perf(ex, crates_count, res, dest)?;
+        write_diagnostics(ex, crates_count, res, dest)?;
+    }
+    write_future_incompat(ex, crates_count, res, dest)?;
+    if ex.mode == Mode::UnstableFeatures {
+        write_unstable_features(ex, crates_count, res, dest)?;
+    }
     write_downloads(ex, crates_count, available_archives, dest)?;
 
     info!("copying static assets");
@@ -1,9 +1,12 @@
 use crate::config::Config;
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::{Crate, DependencyGraph};
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::results::{EncodedLog, EncodingType, ReadResults, TestResult};
-use crate::toolchain::Toolchain;
+use crate::results::{
+    DiagnosticCount, EncodedLog, EncodingType, FutureIncompat, ReadResults, StepMetrics,
+    TestResult, UnstableFeature,
+};
+use crate::toolchain::{FeatureSet, Toolchain};
 use crate::utils;
 use mime::{self, Mime};
 use percent_encoding::{utf8_percent_encode, AsciiSet};
@@ -18,10 +21,17 @@ use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
+mod analyzer;
 mod archives;
+mod diagnostics;
+mod future_incompat;
 mod html;
+mod perf;
 mod s3;
+mod unstable_features;
 
+use self::analyzer::LogAnalysis;
+pub use self::analyzer::RootCause;
 pub use self::s3::{get_client_for_bucket, S3Prefix, S3Writer};
 
 pub(crate) const REPORT_ENCODE_SET: AsciiSet = percent_encoding::CONTROLS
@@ -45,8 +55,38 @@ pub struct TestResults {
 pub struct CrateResult {
     name: String,
     url: String,
+    /// The feature set the crate was built with, in experiments testing the feature matrix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    features: Option<FeatureSet>,
+    /// The target the crate was checked for, if it's not the host target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
     pub res: Comparison,
-    runs: [Option<BuildTestResult>; 2],
+    runs: Vec<Option<BuildTestResult>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regressed_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    causes: Vec<RootCause>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_crate: Option<String>,
+}
+
+impl CrateResult {
+    /// A crate with the given runs and no other information, for the tests of the report pages.
+    #[cfg(test)]
+    fn dummy(name: &str, runs: Vec<Option<BuildTestResult>>) -> Self {
+        CrateResult {
+            name: name.into(),
+            url: String::new(),
+            features: None,
+            target: None,
+            res: Comparison::SameTestPass,
+            runs,
+            regressed_at: None,
+            causes: Vec::new(),
+            root_crate: None,
+        }
+    }
 }
 
 string_enum!(pub enum Comparison {
@@ -81,12 +121,73 @@ impl Comparison {
             | Comparison::SameTestPass => false,
         }
     }
+
+    /// When a crate is tested with more than two toolchains, it's put in the category of the most
+    /// relevant comparison between consecutive toolchains.
+    fn relevance(self) -> u8 {
+        match self {
+            Comparison::SameTestPass => 0,
+            Comparison::SameTestSkipped => 1,
+            Comparison::SameTestFail => 2,
+            Comparison::SameBuildFail => 3,
+            Comparison::Skipped => 4,
+            Comparison::Broken => 5,
+            Comparison::Unknown => 6,
+            Comparison::Error => 7,
+            Comparison::SpuriousFixed => 8,
+            Comparison::Fixed => 9,
+            Comparison::SpuriousRegressed => 10,
+            Comparison::Regressed => 11,
+        }
+    }
+
+    pub fn show_in_baseline_summary(self) -> bool {
+        match self {
+            Comparison::Unknown
+            | Comparison::Error
+            | Comparison::Broken
+            | Comparison::SameBuildFail
+            | Comparison::SameTestFail => true,
+            Comparison::Skipped
+            | Comparison::SameTestSkipped
+            | Comparison::SameTestPass
+            | Comparison::Regressed
+            | Comparison::Fixed
+            | Comparison::SpuriousRegressed
+            | Comparison::SpuriousFixed => false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct BuildTestResult {
     res: TestResult,
     log: String,
+    attempts: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    metrics: Vec<StepMetrics>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<DiagnosticCount>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    future_incompats: Vec<FutureIncompat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unstable_features: Vec<UnstableFeature>,
+}
+
+impl BuildTestResult {
+    /// A run with the given result and nothing recorded, for the tests of the report pages.
+    #[cfg(test)]
+    fn dummy(res: TestResult) -> Self {
+        BuildTestResult {
+            res,
+            log: String::new(),
+            attempts: 1,
+            metrics: Vec::new(),
+            diagnostics: Vec::new(),
+            future_incompats: Vec::new(),
+            unstable_features: Vec::new(),
+        }
+    }
 }
 
 /// The type of sanitization required for a string.
@@ -133,6 +234,23 @@ fn crate_to_path_fragment(
             path.push("local");
             path.push(name);
         }
+        Crate::Git(ref repo) => {
+            path.push("git");
+
+            let mut name = repo.url.clone();
+            if let Some(ref rev) = repo.rev {
+                name.push('#');
+                name.push_str(rev);
+            }
+            path.push(dest.sanitize(&name).into_owned());
+        }
+        Crate::AltRegistry(ref details) => {
+            path.push("alt");
+            path.push(dest.sanitize(&details.registry).into_owned());
+
+            let name = format!("{}-{}", details.name, details.version);
+            path.push(dest.sanitize(&name).into_owned());
+        }
     }
 
     path
@@ -143,16 +261,23 @@ pub fn generate_report<DB: ReadResults>(
     config: &Config,
     ex: &Experiment,
     crates: &[Crate],
+    deps: &DependencyGraph,
 ) -> Fallible<TestResults> {
     let shas = db.load_all_shas(ex)?;
     let mut crates = crates.to_vec();
     //crate ids are unique so unstable sort is equivalent to stable sort but is generally faster
     crates.sort_unstable_by(|a, b| a.id().cmp(&b.id()));
+    let toolchain_sets = ex.toolchain_sets();
     let res = crates
         .iter()
-        .map(|krate| {
+        .flat_map(|krate| {
+            toolchain_sets
+                .iter()
+                .map(move |toolchains| (krate, toolchains))
+        })
+        .map(|(krate, toolchains)| {
             // Any errors here will turn into unknown results
-            let crate_results = ex.toolchains.iter().map(|tc| -> Fallible<BuildTestResult> {
+            let crate_results = toolchains.iter().map(|tc| -> Fallible<BuildTestResult> {
                 let res = db
                     .load_test_result(ex, tc, &krate)?
                     .ok_or_else(|| err_msg("no result"))?;
@@ -163,24 +288,45 @@ pub fn generate_report<DB: ReadResults>(
                         .to_str()
                         .unwrap()
                         .replace(r"\", "/"), // Normalize paths in reports generated on Windows
+                    attempts: db.load_attempts(ex, tc, &krate)?,
+                    metrics: db.load_metrics(ex, tc, &krate)?,
+                    diagnostics: db.load_diagnostics(ex, tc, &krate)?,
+                    future_incompats: db.load_future_incompats(ex, tc, &krate)?,
+                    unstable_features: db.load_unstable_features(ex, tc, &krate)?,
                 })
             });
             // Convert errors to Nones
-            let mut crate_results = crate_results.map(|r| r.ok()).collect::<Vec<_>>();
-            let crate2 = crate_results.pop().unwrap();
-            let crate1 = crate_results.pop().unwrap();
-            let comp = compare(
-                config,
-                &krate,
-                crate1.as_ref().map(|b| b.res),
-                crate2.as_ref().map(|b| b.res),
-            );
+            let crate_results = crate_results.map(|r| r.ok()).collect::<Vec<_>>();
+            let results = crate_results
+                .iter()
+                .map(|r| r.as_ref().map(|b| b.res))
+                .collect::<Vec<_>>();
+            let (comp, regressed_at) = categorize(config, &krate, &results);
+
+            // Look for the root cause of the failure in the log of the regressed run
+            let analysis = match (regressed_at, regressed_at.and_then(|i| results[i])) {
+                (Some(i), Some(res)) => analyzer::analyze(db, ex, &toolchains[i], krate, res, deps)
+                    .unwrap_or_else(|e| {
+                        utils::report_failure(&e);
+                        LogAnalysis::default()
+                    }),
+                _ => LogAnalysis::default(),
+            };
 
             Ok(CrateResult {
                 name: crate_to_name(&krate, &shas)?,
-                url: crate_to_url(&krate, &shas)?,
+                url: crate_to_url(&krate, &shas, config)?,
+                features: if ex.feature_matrix {
+                    Some(toolchains[0].features)
+                } else {
+                    None
+                },
+                target: toolchains[0].target.clone(),
                 res: comp,
-                runs: [crate1, crate2],
+                runs: crate_results,
+                regressed_at: regressed_at.map(|i| toolchains[i].to_string()),
+                causes: analysis.causes,
+                root_crate: analysis.root_crate,
             })
         })
         .collect::<Fallible<Vec<_>>>()?;
@@ -208,7 +354,7 @@ fn write_logs<DB: ReadResults, W: ReportWriter>(
             continue;
         }
 
-        for tc in &ex.toolchains {
+        for tc in &ex.all_toolchains() {
             let log_path =
                 crate_to_path_fragment(tc, krate, SanitizationContext::Path).join("log.txt");
             let content = db
@@ -242,8 +388,9 @@ pub fn gen<DB: ReadResults, W: ReportWriter + Display>(
     crates: &[Crate],
     dest: &W,
     config: &Config,
+    deps: &DependencyGraph,
 ) -> Fallible<TestResults> {
-    let res = generate_report(db, config, ex, crates)?;
+    let res = generate_report(db, config, ex, crates, deps)?;
 
     info!("writing results to {}", dest);
     info!("writing metadata");
@@ -261,35 +408,48 @@ pub fn gen<DB: ReadResults, W: ReportWriter + Display>(
     info!("writing archives");
     let available_archives = archives::write_logs_archives(db, ex, crates, dest, config)?;
     info!("writing html files");
-    html::write_html_report(ex, crates.len(), &res, available_archives, dest)?;
+    html::write_html_report(ex, crates.len(), &res, available_archives, deps, dest)?;
     info!("writing logs");
     write_logs(db, ex, crates, dest, config)?;
 
     Ok(res)
 }
 
-fn crate_to_name(c: &Crate, shas: &HashMap<GitHubRepo, String>) -> Fallible<String> {
+fn crate_to_name(c: &Crate, shas: &HashMap<Crate, String>) -> Fallible<String> {
     Ok(match *c {
         Crate::Registry(ref details) => format!("{}-{}", details.name, details.version),
         Crate::GitHub(ref repo) => {
-            if let Some(sha) = shas.get(repo) {
+            if let Some(sha) = shas.get(c) {
                 format!("{}.{}.{}", repo.org, repo.name, sha)
             } else {
                 format!("{}.{}", repo.org, repo.name)
             }
         }
         Crate::Local(ref name) => format!("{} (local)", name),
+        Crate::AltRegistry(ref details) => {
+            format!(
+                "{}-{} ({})",
+                details.name, details.version, details.registry
+            )
+        }
+        Crate::Git(ref repo) => {
+            if let Some(sha) = shas.get(c) {
+                format!("{}#{}", repo.url, sha)
+            } else {
+                repo.url.clone()
+            }
+        }
     })
 }
 
-fn crate_to_url(c: &Crate, shas: &HashMap<GitHubRepo, String>) -> Fallible<String> {
+fn crate_to_url(c: &Crate, shas: &HashMap<Crate, String>, config: &Config) -> Fallible<String> {
     Ok(match *c {
         Crate::Registry(ref details) => format!(
             "https://crates.io/crates/{}/{}",
             details.name, details.version
         ),
         Crate::GitHub(ref repo) => {
-            if let Some(sha) = shas.get(repo) {
+            if let Some(sha) = shas.get(c) {
                 format!("https://github.com/{}/{}/tree/{}", repo.org, repo.name, sha)
             } else {
                 format!("https://github.com/{}/{}", repo.org, repo.name)
@@ -300,10 +460,25 @@ fn crate_to_url(c: &Crate, shas: &HashMap<GitHubRepo, String>) -> Fallible<Strin
             crate::CRATER_REPO_URL,
             name
         ),
+        // Registries removed from the configuration after the experiment have no URL anymore,
+        // so their crates are only shown by name
+        Crate::AltRegistry(ref details) => details.download_url(config).unwrap_or_default(),
+        Crate::Git(ref repo) => {
+            let url = repo.url.trim_end_matches('/').trim_end_matches(".git");
+            match shas.get(c) {
+                Some(sha) if url.starts_with("https://github.com/") => {
+                    format!("{}/tree/{}", url, sha)
+                }
+                Some(sha) if url.starts_with("https://gitlab.com/") => {
+                    format!("{}/-/tree/{}", url, sha)
+                }
+                _ => repo.url.clone(),
+            }
+        }
     })
 }
 
-fn compare(
+pub(crate) fn compare(
     config: &Config,
     krate: &Crate,
     r1: Option<TestResult>,
@@ -367,6 +542,96 @@ fn compare(
     }
 }
 
+/// Categorize the results of a crate on every toolchain of the experiment, returning the
+/// category and the index of the first toolchain the crate regressed on.
+///
+/// Each toolchain is compared with the previous one, and the most relevant of those comparisons
+/// is picked as the category of the crate.
+fn categorize(
+    config: &Config,
+    krate: &Crate,
+    results: &[Option<TestResult>],
+) -> (Comparison, Option<usize>) {
+    if results.len() == 1 {
+        return (baseline(config, krate, results[0]), None);
+    }
+
+    let comparisons = results
+        .windows(2)
+        .map(|pair| compare(config, krate, pair[0], pair[1]))
+        .collect::<Vec<_>>();
+    let (position, comparison) = comparisons
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, comparison)| comparison.relevance())
+        .unwrap();
+
+    let regressed_at = match comparison {
+        Comparison::Regressed | Comparison::SpuriousRegressed => Some(position + 1),
+        _ => None,
+    };
+
+    (*comparison, regressed_at)
+}
+
+/// A crate that regressed between two toolchains of an experiment.
+pub(crate) struct Regression {
+    pub(crate) krate: Crate,
+    pub(crate) comparison: Comparison,
+    pub(crate) last_good: Toolchain,
+    pub(crate) first_bad: Toolchain,
+}
+
+/// Find the crates that regressed in an experiment, along with their comparison and the
+/// toolchains they were compared with. Crates regressing with multiple feature sets are only
+/// returned once, with the first feature set they regressed with.
+pub(crate) fn find_regressions<DB: ReadResults>(
+    db: &DB,
+    config: &Config,
+    ex: &Experiment,
+    crates: &[Crate],
+) -> Fallible<Vec<Regression>> {
+    let mut regressions = Vec::new();
+    for krate in crates {
+        for toolchains in ex.toolchain_sets() {
+            let results = toolchains
+                .iter()
+                .map(|tc| db.load_test_result(ex, tc, krate))
+                .collect::<Fallible<Vec<_>>>()?;
+
+            if let (comparison, Some(position)) = categorize(config, krate, &results) {
+                regressions.push(Regression {
+                    krate: krate.clone(),
+                    comparison,
+                    last_good: toolchains[position - 1].clone(),
+                    first_bad: toolchains[position].clone(),
+                });
+                break;
+            }
+        }
+    }
+
+    Ok(regressions)
+}
+
+/// Categorize the result of a crate in a baseline experiment, where there is nothing to compare
+/// it against.
+fn baseline(config: &Config, krate: &Crate, res: Option<TestResult>) -> Comparison {
+    use crate::results::TestResult::*;
+
+    match res {
+        Some(BuildFail(_)) => Comparison::SameBuildFail,
+        Some(TestFail(_)) => Comparison::SameTestFail,
+        Some(TestSkipped) => Comparison::SameTestSkipped,
+        Some(TestPass) => Comparison::SameTestPass,
+        Some(Error) => Comparison::Error,
+        Some(BrokenCrate(_)) => Comparison::Broken,
+        None if config.should_skip(krate) => Comparison::Skipped,
+        None => Comparison::Unknown,
+    }
+}
+
 pub trait ReportWriter {
     fn write_bytes<P: AsRef<Path>>(
         &self,
@@ -487,8 +752,8 @@ impl Display for DummyWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Config, CrateConfig};
-    use crate::crates::{Crate, GitHubRepo, RegistryCrate};
+    use crate::config::{Config, CrateConfig, RegistryConfig};
+    use crate::crates::{AltRegistryCrate, Crate, GitHubRepo, GitRepo, RegistryCrate};
     use crate::experiments::{CapLints, Experiment, Mode, Status};
     use crate::results::{BrokenReason, DummyDB, FailureReason, TestResult};
     use crate::toolchain::{MAIN_TOOLCHAIN, TEST_TOOLCHAIN};
@@ -537,10 +802,15 @@ mod tests {
             org: "brson".into(),
             name: "hello-rs".into(),
         };
-        let gh = Crate::GitHub(repo.clone());
+        let gh = Crate::GitHub(repo);
+        let git = Crate::Git(GitRepo {
+            url: "https://gitlab.com/foo/bar.git".into(),
+            rev: None,
+        });
 
         let mut shas = HashMap::new();
-        shas.insert(repo, "f00".into());
+        shas.insert(gh.clone(), "f00".into());
+        shas.insert(git.clone(), "b4r".into());
 
         assert_eq!(
             crate_to_name(&reg, &shas).unwrap(),
@@ -550,6 +820,10 @@ mod tests {
             crate_to_name(&gh, &shas).unwrap(),
             "brson.hello-rs.f00".to_string()
         );
+        assert_eq!(
+            crate_to_name(&git, &shas).unwrap(),
+            "https://gitlab.com/foo/bar.git#b4r".to_string()
+        );
     }
 
     #[test]
@@ -562,19 +836,61 @@ mod tests {
             org: "brson".into(),
             name: "hello-rs".into(),
         };
-        let gh = Crate::GitHub(repo.clone());
+        let gh = Crate::GitHub(repo);
+        let alt = Crate::AltRegistry(AltRegistryCrate {
+            registry: "internal".into(),
+            name: "foo".into(),
+            version: "1.0.0".into(),
+            checksum: String::new(),
+        });
+        let git = Crate::Git(GitRepo {
+            url: "https://gitlab.com/foo/bar.git".into(),
+            rev: None,
+        });
+        let local_git = Crate::Git(GitRepo {
+            url: "/srv/git/bar.git".into(),
+            rev: None,
+        });
 
         let mut shas = HashMap::new();
-        shas.insert(repo, "f00".into());
+        shas.insert(gh.clone(), "f00".into());
+        shas.insert(git.clone(), "b4r".into());
+        shas.insert(local_git.clone(), "b4r".into());
+
+        let mut config = Config::default();
+        config.registries.insert(
+            "internal".into(),
+            RegistryConfig {
+                index: "https://git.example.com/index".into(),
+                download_url: "https://dl.example.com/{crate}-{version}.crate".into(),
+                crates: HashMap::new(),
+            },
+        );
 
         assert_eq!(
-            crate_to_url(&reg, &shas).unwrap(),
+            crate_to_url(&reg, &shas, &config).unwrap(),
             "https://crates.io/crates/lazy_static/1.0".to_string()
         );
         assert_eq!(
-            crate_to_url(&gh, &shas).unwrap(),
+            crate_to_url(&gh, &shas, &config).unwrap(),
             "https://github.com/brson/hello-rs/tree/f00".to_string()
         );
+        assert_eq!(
+            crate_to_url(&alt, &shas, &config).unwrap(),
+            "https://dl.example.com/foo-1.0.0.crate".to_string()
+        );
+        assert_eq!(
+            crate_to_url(&alt, &shas, &Config::default()).unwrap(),
+            String::new()
+        );
+        assert_eq!(
+            crate_to_url(&git, &shas, &config).unwrap(),
+            "https://gitlab.com/foo/bar/-/tree/b4r".to_string()
+        );
+        assert_eq!(
+            crate_to_url(&local_git, &shas, &config).unwrap(),
+            "/srv/git/bar.git".to_string()
+        );
     }
 
     #[test]
@@ -671,19 +987,96 @@ mod tests {
         assert_eq!(compare(&config, &reg, None, None), Comparison::Skipped);
     }
 
+    #[test]
+    fn test_baseline() {
+        use crate::results::{FailureReason::*, TestResult::*};
+
+        let mut config = Config::default();
+        let reg = Crate::Registry(RegistryCrate {
+            name: "lazy_static".into(),
+            version: "1.0".into(),
+        });
+
+        for &(res, expected) in &[
+            (BuildFail(Unknown), Comparison::SameBuildFail),
+            (BuildFail(OOM), Comparison::SameBuildFail),
+            (TestFail(Unknown), Comparison::SameTestFail),
+            (TestSkipped, Comparison::SameTestSkipped),
+            (TestPass, Comparison::SameTestPass),
+            (Error, Comparison::Error),
+            (BrokenCrate(BrokenReason::Yanked), Comparison::Broken),
+        ] {
+            assert_eq!(baseline(&config, &reg, Some(res)), expected);
+        }
+
+        assert_eq!(baseline(&config, &reg, None), Comparison::Unknown);
+
+        config.crates.insert(
+            "lazy_static".into(),
+            CrateConfig {
+                skip: true,
+                skip_tests: false,
+                quiet: false,
+                broken: false,
+            },
+        );
+        assert_eq!(baseline(&config, &reg, None), Comparison::Skipped);
+    }
+
+    #[test]
+    fn test_categorize() {
+        use crate::results::{FailureReason::*, TestResult::*};
+
+        let config = Config::default();
+        let reg = Crate::Registry(RegistryCrate {
+            name: "lazy_static".into(),
+            version: "1.0".into(),
+        });
+
+        macro_rules! test_categorize {
+            ($([$($res:expr),*] => $cmp:ident, $at:expr;)*) => {
+                $(
+                    assert_eq!(
+                        categorize(&config, &reg, &[$(Some($res)),*]),
+                        (Comparison::$cmp, $at)
+                    );
+                )*
+            }
+        }
+
+        test_categorize!(
+            [TestPass] => SameTestPass, None;
+            [TestPass, BuildFail(Unknown)] => Regressed, Some(1);
+            [TestPass, TestPass, TestPass] => SameTestPass, None;
+            [TestPass, TestPass, BuildFail(Unknown), BuildFail(Unknown)] => Regressed, Some(2);
+            [TestPass, TestFail(Unknown), BuildFail(Unknown)] => Regressed, Some(1);
+            [TestPass, TestFail(OOM), TestPass, BuildFail(Unknown)] => Regressed, Some(3);
+            [BuildFail(Unknown), TestPass, TestPass] => Fixed, None;
+            [BuildFail(Unknown), TestPass, BuildFail(Unknown)] => Regressed, Some(2);
+            [TestPass, Error, TestPass] => Error, None;
+        );
+
+        assert_eq!(
+            categorize(&config, &reg, &[Some(TestPass), None, Some(TestPass)]),
+            (Comparison::Unknown, None)
+        );
+    }
+
     #[test]
     fn test_report_generation() {
+        const BETA_LOG: &[u8] = b"error[E0308]: mismatched types\nerror: could not compile `libc`";
+
         let config = Config::default();
 
         let repo = GitHubRepo {
             org: "brson".into(),
             name: "hello-rs".into(),
         };
-        let gh = Crate::GitHub(repo.clone());
+        let gh = Crate::GitHub(repo);
 
         let ex = Experiment {
             name: "foo".to_string(),
-            toolchains: [MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
+            toolchains: vec![MAIN_TOOLCHAIN.clone(), TEST_TOOLCHAIN.clone()],
             mode: Mode::BuildAndTest,
             cap_lints: CapLints::Forbid,
             priority: 0,
@@ -696,10 +1089,13 @@ mod tests {
             report_url: None,
             ignore_blacklist: false,
             requirement: None,
+            crates_seed: None,
+            feature_matrix: false,
+            targets: Vec::new(),
         };
 
         let mut db = DummyDB::default();
-        db.add_dummy_sha(&ex, repo.clone(), "f00".to_string());
+        db.add_dummy_sha(&ex, gh.clone(), "f00".to_string());
         db.add_dummy_result(
             &ex,
             gh.clone(),
@@ -712,6 +1108,7 @@ mod tests {
             TEST_TOOLCHAIN.clone(),
             TestResult::BuildFail(FailureReason::Unknown),
         );
+        db.add_dummy_attempts(&ex, gh.clone(), TEST_TOOLCHAIN.clone(), 2);
         db.add_dummy_log(
             &ex,
             gh.clone(),
@@ -722,11 +1119,15 @@ mod tests {
             &ex,
             gh.clone(),
             TEST_TOOLCHAIN.clone(),
-            EncodedLog::Plain(b"beta log".to_vec()),
+            EncodedLog::Plain(BETA_LOG.to_vec()),
         );
 
+        let deps = vec![("rand".to_string(), "libc".to_string())]
+            .into_iter()
+            .collect::<DependencyGraph>();
+
         let writer = DummyWriter::default();
-        gen(&db, &ex, &[gh], &writer, &config).unwrap();
+        gen(&db, &ex, &[gh], &writer, &config, &deps).unwrap();
 
         assert_eq!(
             writer.get("config.json", &mime::APPLICATION_JSON),
@@ -739,7 +1140,7 @@ mod tests {
         );
         assert_eq!(
             &writer.get("beta/gh/brson.hello-rs/log.txt", &mime::TEXT_PLAIN_UTF_8),
-            b"beta log"
+            BETA_LOG
         );
 
         let result: TestResults =
@@ -770,5 +1171,102 @@ mod tests {
             (&crate_result.runs[1]).as_ref().unwrap().log.as_str(),
             "beta/gh/brson.hello-rs"
         );
+        assert_eq!((&crate_result.runs[0]).as_ref().unwrap().attempts, 1);
+        assert_eq!((&crate_result.runs[1]).as_ref().unwrap().attempts, 2);
+        assert_eq!(
+            crate_result.causes,
+            vec![RootCause::ErrorCode("E0308".into())]
+        );
+        assert_eq!(crate_result.root_crate, Some("libc".into()));
+
+        let causes = writer.get("causes.html", &mime::TEXT_HTML);
+        assert!(String::from_utf8(causes).unwrap().contains("error[E0308]"));
+        let roots = writer.get("roots.html", &mime::TEXT_HTML);
+        assert!(String::from_utf8(roots).unwrap().contains("libc"));
+        assert!(!writer.get("perf.html", &mime::TEXT_HTML).is_empty());
+        assert!(!writer.get("diagnostics.html", &mime::TEXT_HTML).is_empty());
+        assert!(!writer
+            .get("future-incompat.html", &mime::TEXT_HTML)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_baseline_report_generation() {
+        let config = Config::default();
+
+        let reg = Crate::Registry(RegistryCrate {
+            name: "lazy_static".into(),
+            version: "1.0".into(),
+        });
+
+        let ex = Experiment {
+            name: "foo".to_string(),
+            toolchains: vec![MAIN_TOOLCHAIN.clone()],
+            mode: Mode::BuildAndTest,
+            cap_lints: CapLints::Forbid,
+            priority: 0,
+            created_at: ::chrono::Utc::now(),
+            started_at: None,
+            completed_at: None,
+            github_issue: None,
+            status: Status::GeneratingReport,
+            assigned_to: None,
+            report_url: None,
+            ignore_blacklist: false,
+            requirement: None,
+            crates_seed: None,
+            feature_matrix: false,
+            targets: Vec::new(),
+        };
+
+        let mut db = DummyDB::default();
+        db.add_dummy_result(
+            &ex,
+            reg.clone(),
+            MAIN_TOOLCHAIN.clone(),
+            TestResult::TestFail(FailureReason::Unknown),
+        );
+        db.add_dummy_log(
+            &ex,
+            reg.clone(),
+            MAIN_TOOLCHAIN.clone(),
+            EncodedLog::Plain(b"stable log".to_vec()),
+        );
+
+        let writer = DummyWriter::default();
+        gen(
+            &db,
+            &ex,
+            &[reg],
+            &writer,
+            &config,
+            &DependencyGraph::default(),
+        )
+        .unwrap();
+
+        let result: TestResults =
+            serde_json::from_slice(&writer.get("results.json", &mime::APPLICATION_JSON)).unwrap();
+
+        assert_eq!(result.crates.len(), 1);
+        let crate_result = &result.crates[0];
+
+        assert_eq!(crate_result.res, Comparison::SameTestFail);
+        assert_eq!(crate_result.runs.len(), 1);
+        assert_eq!(
+            (&crate_result.runs[0]).as_ref().unwrap().log.as_str(),
+            "stable/reg/lazy_static-1.0"
+        );
+
+        // There's no second toolchain to compare with
+        for page in &["perf.html", "diagnostics.html"] {
+            assert!(!writer
+                .results
+                .borrow()
+                .contains_key(&(std::path::PathBuf::from(page), mime::TEXT_HTML)));
+        }
+        // The future incompatibilities only need the results of the last toolchain
+        assert!(!writer
+            .get("future-incompat.html", &mime::TEXT_HTML)
+            .is_empty());
     }
 }
@@ -0,0 +1,203 @@
+use crate::report::{BuildTestResult, TestResults};
+use crate::results::{Step, TestResult};
+use crate::toolchain::FeatureSet;
+
+/// How much slower a build has to be, after the noise is filtered out, to be flagged.
+const THRESHOLD: f64 = 0.1;
+/// Builds faster than this on both toolchains are dominated by noise, in milliseconds.
+const MIN_DURATION: f64 = 1000.0;
+
+string_enum!(pub enum PerfComparison {
+    Regressed => "regressed",
+    Improved => "improved",
+    Unchanged => "unchanged",
+    Unknown => "unknown",
+});
+
+/// The compile time of a crate on the first two toolchains of the experiment.
+#[derive(Serialize, Clone)]
+pub struct PerfResult {
+    name: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    features: Option<FeatureSet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    pub res: PerfComparison,
+    /// Median build durations on the two toolchains, in seconds.
+    duration: [f64; 2],
+    /// Change of the median build duration, in percent.
+    duration_change: f64,
+    /// Spread of the repeated builds of the crate on a single toolchain, in percent.
+    noise: f64,
+}
+
+/// The median and the relative spread of the build durations of a run, if it built successfully.
+fn build_duration(run: &Option<BuildTestResult>) -> Option<(f64, f64)> {
+    let run = run.as_ref()?;
+    match run.res {
+        TestResult::BrokenCrate(_) | TestResult::BuildFail(_) | TestResult::Error => return None,
+        TestResult::TestFail(_) | TestResult::TestSkipped | TestResult::TestPass => {}
+    }
+
+    // Every build step is a sample of the same build, repeated from scratch
+    let samples = run
+        .metrics
+        .iter()
+        .filter(|m| m.step == Step::Build)
+        .map(|m| m.duration as f64)
+        .collect::<Vec<_>>();
+    let median = median(&samples)?;
+
+    let min = samples.iter().cloned().fold(std::f64::INFINITY, f64::min);
+    let max = samples.iter().cloned().fold(0.0, f64::max);
+    Some((median, (max - min) / median.max(1.0)))
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut values = values.to_vec();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Compare the compile times of the crates between the first two toolchains.
+///
+/// Build durations fluctuate with the load of the machines running the experiment, so a crate is
+/// only flagged when its change exceeds the spread of its own repeated builds on top of the
+/// threshold, and builds too short to be measured reliably are never flagged.
+pub fn compare_perf(res: &TestResults) -> Vec<PerfResult> {
+    res.crates
+        .iter()
+        .map(|krate| {
+            let mut result = PerfResult {
+                name: krate.name.clone(),
+                url: krate.url.clone(),
+                features: krate.features,
+                target: krate.target.clone(),
+                res: PerfComparison::Unknown,
+                duration: [0.0, 0.0],
+                duration_change: 0.0,
+                noise: 0.0,
+            };
+
+            let ((before, noise_before), (after, noise_after)) = match (
+                krate.runs.get(0).and_then(build_duration),
+                krate.runs.get(1).and_then(build_duration),
+            ) {
+                (Some(before), Some(after)) => (before, after),
+                _ => return result,
+            };
+
+            let ratio = after / before.max(1.0);
+            let noise = noise_before.max(noise_after);
+            result.duration = [before / 1000.0, after / 1000.0];
+            result.duration_change = (ratio - 1.0) * 100.0;
+            result.noise = noise * 100.0;
+
+            let measurable = before >= MIN_DURATION || after >= MIN_DURATION;
+            let threshold = 1.0 + THRESHOLD + noise;
+            result.res = if measurable && ratio > threshold {
+                PerfComparison::Regressed
+            } else if measurable && ratio < 1.0 / threshold {
+                PerfComparison::Improved
+            } else {
+                PerfComparison::Unchanged
+            };
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_perf, median, PerfComparison};
+    use crate::report::{BuildTestResult, CrateResult, TestResults};
+    use crate::results::{FailureReason, Step, StepMetrics, TestResult};
+
+    fn run(res: TestResult, durations: &[u64]) -> Option<BuildTestResult> {
+        let mut metrics = vec![StepMetrics {
+            step: Step::Prepare,
+            duration: 100_000,
+            target_dir_size: None,
+        }];
+        for &duration in durations {
+            metrics.push(StepMetrics {
+                step: Step::Build,
+                duration,
+                target_dir_size: None,
+            });
+        }
+
+        Some(BuildTestResult {
+            metrics,
+            ..BuildTestResult::dummy(res)
+        })
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), Some(2.5));
+    }
+
+    #[test]
+    fn test_compare_perf() {
+        let pass = TestResult::TestPass;
+        let res = TestResults {
+            crates: vec![
+                // Changes below the threshold
+                CrateResult::dummy("a", vec![run(pass, &[10_000]), run(pass, &[10_500])]),
+                // Changes within the noise of the repeated builds
+                CrateResult::dummy(
+                    "b",
+                    vec![run(pass, &[10_000, 14_000]), run(pass, &[13_000, 13_500])],
+                ),
+                // Regressed and improved compile times
+                CrateResult::dummy(
+                    "c",
+                    vec![run(pass, &[10_000, 10_200]), run(pass, &[12_000, 12_100])],
+                ),
+                CrateResult::dummy("d", vec![run(pass, &[10_000]), run(pass, &[5_000])]),
+                // Builds too short to be measured
+                CrateResult::dummy("e", vec![run(pass, &[10]), run(pass, &[100])]),
+                // Failed builds can't be compared
+                CrateResult::dummy(
+                    "f",
+                    vec![
+                        run(pass, &[10_000]),
+                        run(TestResult::BuildFail(FailureReason::Unknown), &[1_000]),
+                    ],
+                ),
+                CrateResult::dummy("g", vec![run(pass, &[10_000]), None]),
+            ],
+        };
+
+        let perf = compare_perf(&res);
+        assert_eq!(
+            perf.iter().map(|p| p.res).collect::<Vec<_>>(),
+            vec![
+                PerfComparison::Unchanged,
+                PerfComparison::Unchanged,
+                PerfComparison::Regressed,
+                PerfComparison::Improved,
+                PerfComparison::Unchanged,
+                PerfComparison::Unknown,
+                PerfComparison::Unknown,
+            ]
+        );
+        assert!((perf[0].duration_change - 5.0).abs() < 0.001);
+        assert!(perf[0].noise.abs() < 0.001);
+        assert!((perf[1].noise - 100.0 / 3.0).abs() < 0.001);
+        assert_eq!(perf[2].duration, [10.1, 12.05]);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::report::TestResults;
+use std::collections::{BTreeMap, HashSet};
+
+/// A crate enabling an unstable feature.
+#[derive(Serialize)]
+pub struct FeatureCrate {
+    name: String,
+    url: String,
+    /// The files of the crate enabling the feature.
+    files: Vec<String>,
+    log: String,
+}
+
+/// An unstable feature, with the crates enabling it.
+#[derive(Serialize)]
+pub struct FeatureUsage {
+    pub name: String,
+    pub crates: Vec<FeatureCrate>,
+}
+
+/// Group the crates by the unstable features they enable. As the features are found by reading
+/// the source code of the crates, only the first toolchain with a result is considered. The most
+/// used features come first. The source code doesn't depend on the feature set or the target the
+/// crates were tested with, so each crate is only counted once.
+pub fn unstable_features(res: &TestResults) -> Vec<FeatureUsage> {
+    let mut usages: BTreeMap<&str, Vec<FeatureCrate>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+
+    for krate in &res.crates {
+        if !seen.insert(krate.name.as_str()) {
+            continue;
+        }
+
+        let run = match krate.runs.iter().flatten().next() {
+            Some(run) => run,
+            None => continue,
+        };
+
+        let mut files: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for feature in &run.unstable_features {
+            files
+                .entry(feature.name.as_str())
+                .or_insert_with(Vec::new)
+                .push(feature.file.clone());
+        }
+
+        for (name, files) in files {
+            usages
+                .entry(name)
+                .or_insert_with(Vec::new)
+                .push(FeatureCrate {
+                    name: krate.name.clone(),
+                    url: krate.url.clone(),
+                    files,
+                    log: run.log.clone(),
+                });
+        }
+    }
+
+    let mut usages = usages
+        .into_iter()
+        .map(|(name, crates)| FeatureUsage {
+            name: name.to_string(),
+            crates,
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so features used by the same number of crates stay sorted by name
+    usages.sort_by(|a, b| b.crates.len().cmp(&a.crates.len()));
+    usages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unstable_features;
+    use crate::report::{BuildTestResult, CrateResult, TestResults};
+    use crate::results::{TestResult, UnstableFeature};
+
+    fn krate(name: &str, features: &[(&str, &str)]) -> CrateResult {
+        let run = BuildTestResult {
+            unstable_features: features
+                .iter()
+                .map(|&(name, file)| UnstableFeature {
+                    name: name.into(),
+                    file: file.into(),
+                })
+                .collect(),
+            ..BuildTestResult::dummy(TestResult::TestPass)
+        };
+        CrateResult::dummy(name, vec![Some(run)])
+    }
+
+    #[test]
+    fn test_unstable_features() {
+        let res = TestResults {
+            crates: vec![
+                krate(
+                    "a",
+                    &[
+                        ("test", "benches/a.rs"),
+                        ("test", "benches/b.rs"),
+                        ("never_type", "src/lib.rs"),
+                    ],
+                ),
+                krate("b", &[("test", "benches/bench.rs")]),
+                // Crates are counted once across feature sets and targets
+                CrateResult {
+                    target: Some("wasm32-unknown-unknown".into()),
+                    ..krate("b", &[("test", "benches/bench.rs")])
+                },
+                krate("c", &[]),
+            ],
+        };
+
+        let usages = unstable_features(&res);
+        assert_eq!(
+            usages
+                .iter()
+                .map(|u| (u.name.as_str(), u.crates.len()))
+                .collect::<Vec<_>>(),
+            vec![("test", 2), ("never_type", 1)]
+        );
+        assert_eq!(
+            usages[0].crates[0].files,
+            vec!["benches/a.rs".to_string(), "benches/b.rs".to_string()]
+        );
+    }
+}
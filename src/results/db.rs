@@ -1,10 +1,11 @@
 use crate::config::Config;
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::Crate;
 use crate::db::{Database, QueryUtils};
 use crate::experiments::Experiment;
 use crate::prelude::*;
 use crate::results::{
-    DeleteResults, EncodedLog, EncodingType, ReadResults, TestResult, WriteResults,
+    DeleteResults, DiagnosticCount, EncodedLog, EncodingType, FutureIncompat, ReadResults, Step,
+    StepMetrics, TestResult, UnstableFeature, WriteResults,
 };
 use crate::toolchain::Toolchain;
 use base64;
@@ -19,12 +20,26 @@ pub struct TaskResult {
     pub toolchain: Toolchain,
     pub result: TestResult,
     pub log: String,
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    #[serde(default)]
+    pub metrics: Vec<StepMetrics>,
+    #[serde(default)]
+    pub diagnostics: Vec<DiagnosticCount>,
+    #[serde(default)]
+    pub future_incompats: Vec<FutureIncompat>,
+    #[serde(default)]
+    pub unstable_features: Vec<UnstableFeature>,
+}
+
+fn default_attempts() -> u32 {
+    1
 }
 
 #[derive(Deserialize)]
 pub struct ProgressData {
     pub results: Vec<TaskResult>,
-    pub shas: Vec<(GitHubRepo, String)>,
+    pub shas: Vec<(Crate, String)>,
 }
 
 pub struct DatabaseDB<'a> {
@@ -51,10 +66,36 @@ impl<'a> DatabaseDB<'a> {
                 &base64::decode(&result.log).with_context(|_| "invalid base64 log provided")?,
                 encoding_type,
             )?;
+
+            if result.attempts > 1 {
+                self.record_attempts(ex, &result.toolchain, &result.krate, result.attempts)?;
+            }
+            if !result.metrics.is_empty() {
+                self.record_metrics(ex, &result.toolchain, &result.krate, &result.metrics)?;
+            }
+            if !result.diagnostics.is_empty() {
+                self.record_diagnostics(ex, &result.toolchain, &result.krate, &result.diagnostics)?;
+            }
+            if !result.future_incompats.is_empty() {
+                self.record_future_incompats(
+                    ex,
+                    &result.toolchain,
+                    &result.krate,
+                    &result.future_incompats,
+                )?;
+            }
+            if !result.unstable_features.is_empty() {
+                self.record_unstable_features(
+                    ex,
+                    &result.toolchain,
+                    &result.krate,
+                    &result.unstable_features,
+                )?;
+            }
         }
 
-        for &(ref repo, ref sha) in &data.shas {
-            self.record_sha(ex, repo, sha)?;
+        for &(ref krate, ref sha) in &data.shas {
+            self.record_sha(ex, krate, sha)?;
         }
 
         Ok(())
@@ -98,24 +139,17 @@ impl<'a> DatabaseDB<'a> {
 }
 
 impl<'a> ReadResults for DatabaseDB<'a> {
-    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<GitHubRepo, String>> {
-        Ok(self
-            .db
-            .query(
-                "SELECT * FROM shas WHERE experiment = ?1;",
-                &[&ex.name],
-                |row| {
-                    (
-                        GitHubRepo {
-                            org: row.get("org"),
-                            name: row.get("name"),
-                        },
-                        row.get("sha"),
-                    )
-                },
-            )?
-            .into_iter()
-            .collect())
+    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<Crate, String>> {
+        let shas = self.db.query(
+            "SELECT * FROM shas WHERE experiment = ?1;",
+            &[&ex.name],
+            |row| -> Fallible<(Crate, String)> {
+                let krate: String = row.get("crate");
+                Ok((serde_json::from_str(&krate)?, row.get("sha")))
+            },
+        )?;
+
+        shas.into_iter().collect()
     }
 
     fn load_log(
@@ -173,6 +207,142 @@ impl<'a> ReadResults for DatabaseDB<'a> {
             Ok(None)
         }
     }
+
+    fn load_attempts(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<u32> {
+        let attempts = self.db.get_row(
+            "SELECT attempts FROM result_attempts \
+             WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+             LIMIT 1;",
+            &[
+                &ex.name,
+                &toolchain.to_string(),
+                &serde_json::to_string(krate)?,
+            ],
+            |row| row.get("attempts"),
+        )?;
+
+        // Results without a recorded number of attempts were run only once
+        Ok(attempts.unwrap_or(1))
+    }
+
+    fn load_metrics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<StepMetrics>> {
+        let rows = self.db.query(
+            "SELECT step, duration, target_dir_size FROM result_metrics \
+             WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+             ORDER BY rowid;",
+            &[
+                &ex.name,
+                &toolchain.to_string(),
+                &serde_json::to_string(krate)?,
+            ],
+            |row| {
+                (
+                    row.get::<_, String>("step"),
+                    row.get::<_, i64>("duration"),
+                    row.get::<_, Option<i64>>("target_dir_size"),
+                )
+            },
+        )?;
+
+        rows.into_iter()
+            .map(|(step, duration, target_dir_size)| {
+                Ok(StepMetrics {
+                    step: step.parse::<Step>()?,
+                    duration: duration as u64,
+                    target_dir_size: target_dir_size.map(|v| v as u64),
+                })
+            })
+            .collect()
+    }
+
+    fn load_diagnostics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<DiagnosticCount>> {
+        let rows = self.db.query(
+            "SELECT code, level, count FROM result_diagnostics \
+             WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+             ORDER BY code, level;",
+            &[
+                &ex.name,
+                &toolchain.to_string(),
+                &serde_json::to_string(krate)?,
+            ],
+            |row| {
+                (
+                    row.get::<_, String>("code"),
+                    row.get::<_, String>("level"),
+                    row.get::<_, u32>("count"),
+                )
+            },
+        )?;
+
+        rows.into_iter()
+            .map(|(code, level, count)| {
+                Ok(DiagnosticCount {
+                    code,
+                    level: level.parse()?,
+                    count,
+                })
+            })
+            .collect()
+    }
+
+    fn load_future_incompats(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<FutureIncompat>> {
+        self.db.query(
+            "SELECT lint, package FROM result_future_incompats \
+             WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+             ORDER BY lint, package;",
+            &[
+                &ex.name,
+                &toolchain.to_string(),
+                &serde_json::to_string(krate)?,
+            ],
+            |row| FutureIncompat {
+                lint: row.get("lint"),
+                package: row.get("package"),
+            },
+        )
+    }
+
+    fn load_unstable_features(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<UnstableFeature>> {
+        self.db.query(
+            "SELECT feature, file FROM result_unstable_features \
+             WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3 \
+             ORDER BY feature, file;",
+            &[
+                &ex.name,
+                &toolchain.to_string(),
+                &serde_json::to_string(krate)?,
+            ],
+            |row| UnstableFeature {
+                name: row.get("feature"),
+                file: row.get("file"),
+            },
+        )
+    }
 }
 
 impl<'a> WriteResults for DatabaseDB<'a> {
@@ -185,15 +355,155 @@ impl<'a> WriteResults for DatabaseDB<'a> {
         self.load_test_result(ex, toolchain, krate)
     }
 
-    fn record_sha(&self, ex: &Experiment, repo: &GitHubRepo, sha: &str) -> Fallible<()> {
+    fn get_sha(&self, ex: &Experiment, krate: &Crate) -> Fallible<Option<String>> {
+        Ok(self
+            .db
+            .query(
+                "SELECT sha FROM shas WHERE experiment = ?1 AND crate = ?2 LIMIT 1;",
+                &[&ex.name, &serde_json::to_string(krate)?],
+                |row| row.get("sha"),
+            )?
+            .pop())
+    }
+
+    fn record_sha(&self, ex: &Experiment, krate: &Crate, sha: &str) -> Fallible<()> {
         self.db.execute(
-            "INSERT INTO shas (experiment, org, name, sha) VALUES (?1, ?2, ?3, ?4)",
-            &[&ex.name, &repo.org, &repo.name, &sha],
+            "INSERT INTO shas (experiment, crate, sha) VALUES (?1, ?2, ?3)",
+            &[&ex.name, &serde_json::to_string(krate)?, &sha],
         )?;
 
         Ok(())
     }
 
+    fn record_attempts(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        attempts: u32,
+    ) -> Fallible<()> {
+        self.db.execute(
+            "INSERT INTO result_attempts (experiment, crate, toolchain, attempts) \
+             VALUES (?1, ?2, ?3, ?4);",
+            &[
+                &ex.name,
+                &serde_json::to_string(krate)?,
+                &toolchain.to_string(),
+                &attempts,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn record_metrics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        metrics: &[StepMetrics],
+    ) -> Fallible<()> {
+        let krate = serde_json::to_string(krate)?;
+        let toolchain = toolchain.to_string();
+        self.db.transaction(|t| {
+            for metric in metrics {
+                t.execute(
+                    "INSERT INTO result_metrics \
+                     (experiment, crate, toolchain, step, duration, target_dir_size) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                    &[
+                        &ex.name,
+                        &krate,
+                        &toolchain,
+                        &metric.step.to_str(),
+                        &(metric.duration as i64),
+                        &metric.target_dir_size.map(|v| v as i64),
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn record_diagnostics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        diagnostics: &[DiagnosticCount],
+    ) -> Fallible<()> {
+        let krate = serde_json::to_string(krate)?;
+        let toolchain = toolchain.to_string();
+        self.db.transaction(|t| {
+            for diagnostic in diagnostics {
+                t.execute(
+                    "INSERT INTO result_diagnostics \
+                     (experiment, crate, toolchain, code, level, count) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+                    &[
+                        &ex.name,
+                        &krate,
+                        &toolchain,
+                        &diagnostic.code,
+                        &diagnostic.level.to_str(),
+                        &diagnostic.count,
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn record_future_incompats(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        future_incompats: &[FutureIncompat],
+    ) -> Fallible<()> {
+        let krate = serde_json::to_string(krate)?;
+        let toolchain = toolchain.to_string();
+        self.db.transaction(|t| {
+            for future_incompat in future_incompats {
+                t.execute(
+                    "INSERT INTO result_future_incompats \
+                     (experiment, crate, toolchain, lint, package) \
+                     VALUES (?1, ?2, ?3, ?4, ?5);",
+                    &[
+                        &ex.name,
+                        &krate,
+                        &toolchain,
+                        &future_incompat.lint,
+                        &future_incompat.package,
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn record_unstable_features(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        features: &[UnstableFeature],
+    ) -> Fallible<()> {
+        let krate = serde_json::to_string(krate)?;
+        let toolchain = toolchain.to_string();
+        self.db.transaction(|t| {
+            for feature in features {
+                t.execute(
+                    "INSERT INTO result_unstable_features \
+                     (experiment, crate, toolchain, feature, file) \
+                     VALUES (?1, ?2, ?3, ?4, ?5);",
+                    &[&ex.name, &krate, &toolchain, &feature.name, &feature.file],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
     fn record_result<F>(
         &self,
         ex: &Experiment,
@@ -226,18 +536,42 @@ impl<'a> DeleteResults for DatabaseDB<'a> {
     fn delete_all_results(&self, ex: &Experiment) -> Fallible<()> {
         self.db
             .execute("DELETE FROM results WHERE experiment = ?1;", &[&ex.name])?;
+        for table in &[
+            "result_attempts",
+            "result_metrics",
+            "result_diagnostics",
+            "result_future_incompats",
+            "result_unstable_features",
+        ] {
+            self.db.execute(
+                &format!("DELETE FROM {} WHERE experiment = ?1;", table),
+                &[&ex.name],
+            )?;
+        }
         Ok(())
     }
 
     fn delete_result(&self, ex: &Experiment, tc: &Toolchain, krate: &Crate) -> Fallible<()> {
-        self.db.execute(
-            "DELETE FROM results WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3;",
-            &[
-                &ex.name,
-                &tc.to_string(),
-                &serde_json::to_string(krate).unwrap(),
-            ],
-        )?;
+        for table in &[
+            "results",
+            "result_attempts",
+            "result_metrics",
+            "result_diagnostics",
+            "result_future_incompats",
+            "result_unstable_features",
+        ] {
+            self.db.execute(
+                &format!(
+                    "DELETE FROM {} WHERE experiment = ?1 AND toolchain = ?2 AND crate = ?3;",
+                    table
+                ),
+                &[
+                    &ex.name,
+                    &tc.to_string(),
+                    &serde_json::to_string(krate).unwrap(),
+                ],
+            )?;
+        }
         Ok(())
     }
 }
@@ -247,13 +581,13 @@ mod tests {
     use super::{DatabaseDB, ProgressData, TaskResult};
     use crate::actions::{Action, ActionsCtx, CreateExperiment};
     use crate::config::Config;
-    use crate::crates::{Crate, GitHubRepo, RegistryCrate};
+    use crate::crates::{Crate, GitHubRepo, GitRepo, RegistryCrate};
     use crate::db::Database;
     use crate::experiments::Experiment;
     use crate::prelude::*;
     use crate::results::{
-        DeleteResults, EncodedLog, EncodingType, FailureReason, ReadResults, TestResult,
-        WriteResults,
+        DeleteResults, DiagnosticCount, DiagnosticLevel, EncodedLog, EncodingType, FailureReason,
+        FutureIncompat, ReadResults, Step, StepMetrics, TestResult, UnstableFeature, WriteResults,
     };
     use crate::toolchain::{MAIN_TOOLCHAIN, TEST_TOOLCHAIN};
     use base64;
@@ -271,15 +605,15 @@ mod tests {
         CreateExperiment::dummy("dummy").apply(&ctx).unwrap();
         let ex = Experiment::get(&db, "dummy").unwrap().unwrap();
 
-        // Define some dummy GitHub repositories
-        let repo1 = GitHubRepo {
+        // Define some dummy git repositories
+        let repo1 = Crate::GitHub(GitHubRepo {
             org: "foo".to_string(),
             name: "bar".to_string(),
-        };
-        let repo2 = GitHubRepo {
-            org: "foo".to_string(),
-            name: "baz".to_string(),
-        };
+        });
+        let repo2 = Crate::Git(GitRepo {
+            url: "https://gitlab.com/foo/baz".to_string(),
+            rev: None,
+        });
 
         // Store some SHAs for those repos
         results
@@ -305,6 +639,16 @@ mod tests {
         results
             .record_sha(&ex, &repo1, "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa")
             .unwrap();
+        assert_eq!(
+            results.get_sha(&ex, &repo1).unwrap(),
+            Some("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".into())
+        );
+        assert_eq!(
+            results
+                .get_sha(&ex, &Crate::Local("build-pass".into()))
+                .unwrap(),
+            None
+        );
 
         let shas = results.load_all_shas(&ex).unwrap();
         assert_eq!(shas.len(), 2);
@@ -407,12 +751,122 @@ mod tests {
             Some(TestResult::TestFail(FailureReason::Unknown))
         );
 
+        // Results are considered run once unless more attempts are recorded
+        assert_eq!(
+            results.load_attempts(&ex, &TEST_TOOLCHAIN, &krate).unwrap(),
+            1
+        );
+        results
+            .record_attempts(&ex, &TEST_TOOLCHAIN, &krate, 2)
+            .unwrap();
+        assert_eq!(
+            results.load_attempts(&ex, &TEST_TOOLCHAIN, &krate).unwrap(),
+            2
+        );
+
+        // Metrics are returned in the order the steps ran
+        let metrics = vec![
+            StepMetrics {
+                step: Step::Prepare,
+                duration: 1200,
+                target_dir_size: Some(0),
+            },
+            StepMetrics {
+                step: Step::Build,
+                duration: 30_000,
+                target_dir_size: Some(1 << 28),
+            },
+        ];
+        results
+            .record_metrics(&ex, &TEST_TOOLCHAIN, &krate, &metrics)
+            .unwrap();
+        assert_eq!(
+            results.load_metrics(&ex, &TEST_TOOLCHAIN, &krate).unwrap(),
+            metrics
+        );
+
+        let diagnostics = vec![
+            DiagnosticCount {
+                code: "E0308".into(),
+                level: DiagnosticLevel::Error,
+                count: 1,
+            },
+            DiagnosticCount {
+                code: "unused_variables".into(),
+                level: DiagnosticLevel::Warning,
+                count: 3,
+            },
+        ];
+        results
+            .record_diagnostics(&ex, &TEST_TOOLCHAIN, &krate, &diagnostics)
+            .unwrap();
+        assert_eq!(
+            results
+                .load_diagnostics(&ex, &TEST_TOOLCHAIN, &krate)
+                .unwrap(),
+            diagnostics
+        );
+
+        let future_incompats = vec![FutureIncompat {
+            lint: "semicolon_in_expressions_from_macros".into(),
+            package: "foo v0.1.0".into(),
+        }];
+        results
+            .record_future_incompats(&ex, &TEST_TOOLCHAIN, &krate, &future_incompats)
+            .unwrap();
+        assert_eq!(
+            results
+                .load_future_incompats(&ex, &TEST_TOOLCHAIN, &krate)
+                .unwrap(),
+            future_incompats
+        );
+
+        let features = vec![
+            UnstableFeature {
+                name: "never_type".into(),
+                file: "src/lib.rs".into(),
+            },
+            UnstableFeature {
+                name: "test".into(),
+                file: "benches/bench.rs".into(),
+            },
+        ];
+        results
+            .record_unstable_features(&ex, &TEST_TOOLCHAIN, &krate, &features)
+            .unwrap();
+        assert_eq!(
+            results
+                .load_unstable_features(&ex, &TEST_TOOLCHAIN, &krate)
+                .unwrap(),
+            features
+        );
+
         // Test deleting the newly-added result
         results.delete_result(&ex, &TEST_TOOLCHAIN, &krate).unwrap();
         assert!(results
             .get_result(&ex, &TEST_TOOLCHAIN, &krate)
             .unwrap()
             .is_none());
+        assert_eq!(
+            results.load_attempts(&ex, &TEST_TOOLCHAIN, &krate).unwrap(),
+            1
+        );
+        assert!(results
+            .load_metrics(&ex, &TEST_TOOLCHAIN, &krate)
+            .unwrap()
+            .is_empty());
+        assert!(results
+            .load_diagnostics(&ex, &TEST_TOOLCHAIN, &krate)
+            .unwrap()
+            .is_empty());
+        assert!(results
+            .load_future_incompats(&ex, &TEST_TOOLCHAIN, &krate)
+            .unwrap()
+            .is_empty());
+        assert!(results
+            .load_unstable_features(&ex, &TEST_TOOLCHAIN, &krate)
+            .unwrap()
+            .is_empty());
         assert_eq!(
             results.get_result(&ex, &MAIN_TOOLCHAIN, &krate).unwrap(),
             Some(TestResult::TestPass)
@@ -454,20 +908,29 @@ mod tests {
                         toolchain: MAIN_TOOLCHAIN.clone(),
                         result: TestResult::TestPass,
                         log: base64::encode("foo"),
+                        attempts: 3,
+                        metrics: vec![StepMetrics {
+                            step: Step::Test,
+                            duration: 500,
+                            target_dir_size: None,
+                        }],
+                        diagnostics: Vec::new(),
+                        future_incompats: Vec::new(),
+                        unstable_features: Vec::new(),
                     }],
                     shas: vec![
                         (
-                            GitHubRepo {
+                            Crate::GitHub(GitHubRepo {
                                 org: "foo".into(),
                                 name: "bar".into(),
-                            },
+                            }),
                             "42".into(),
                         ),
                         (
-                            GitHubRepo {
+                            Crate::GitHub(GitHubRepo {
                                 org: "foo".into(),
                                 name: "baz".into(),
-                            },
+                            }),
                             "beef".into(),
                         ),
                     ],
@@ -486,5 +949,13 @@ mod tests {
                 .unwrap(),
             Some(TestResult::TestPass)
         );
+        assert_eq!(
+            results.load_attempts(&ex, &MAIN_TOOLCHAIN, &krate).unwrap(),
+            3
+        );
+        assert_eq!(
+            results.load_metrics(&ex, &MAIN_TOOLCHAIN, &krate).unwrap()[0].duration,
+            500
+        );
     }
 }
@@ -1,15 +1,23 @@
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::results::{EncodedLog, ReadResults, TestResult};
+use crate::results::{
+    DiagnosticCount, EncodedLog, FutureIncompat, ReadResults, StepMetrics, TestResult,
+    UnstableFeature,
+};
 use crate::toolchain::Toolchain;
 use std::collections::HashMap;
 
 #[derive(Default)]
 struct DummyData {
-    shas: HashMap<GitHubRepo, String>,
+    shas: HashMap<Crate, String>,
     logs: HashMap<(Crate, Toolchain), EncodedLog>,
     results: HashMap<(Crate, Toolchain), TestResult>,
+    attempts: HashMap<(Crate, Toolchain), u32>,
+    metrics: HashMap<(Crate, Toolchain), Vec<StepMetrics>>,
+    diagnostics: HashMap<(Crate, Toolchain), Vec<DiagnosticCount>>,
+    future_incompats: HashMap<(Crate, Toolchain), Vec<FutureIncompat>>,
+    unstable_features: HashMap<(Crate, Toolchain), Vec<UnstableFeature>>,
 }
 
 #[derive(Default)]
@@ -25,12 +33,12 @@ impl DummyDB {
             .ok_or_else(|| err_msg(format!("missing experiment {}", ex.name)))?)
     }
 
-    pub fn add_dummy_sha(&mut self, ex: &Experiment, repo: GitHubRepo, sha: String) {
+    pub fn add_dummy_sha(&mut self, ex: &Experiment, krate: Crate, sha: String) {
         self.experiments
             .entry(ex.name.to_string())
             .or_insert_with(DummyData::default)
             .shas
-            .insert(repo, sha);
+            .insert(krate, sha);
     }
 
     pub fn add_dummy_log(&mut self, ex: &Experiment, krate: Crate, tc: Toolchain, log: EncodedLog) {
@@ -54,10 +62,80 @@ impl DummyDB {
             .results
             .insert((krate, tc), res);
     }
+
+    pub fn add_dummy_attempts(
+        &mut self,
+        ex: &Experiment,
+        krate: Crate,
+        tc: Toolchain,
+        attempts: u32,
+    ) {
+        self.experiments
+            .entry(ex.name.to_string())
+            .or_insert_with(DummyData::default)
+            .attempts
+            .insert((krate, tc), attempts);
+    }
+
+    pub fn add_dummy_metrics(
+        &mut self,
+        ex: &Experiment,
+        krate: Crate,
+        tc: Toolchain,
+        metrics: Vec<StepMetrics>,
+    ) {
+        self.experiments
+            .entry(ex.name.to_string())
+            .or_insert_with(DummyData::default)
+            .metrics
+            .insert((krate, tc), metrics);
+    }
+
+    pub fn add_dummy_diagnostics(
+        &mut self,
+        ex: &Experiment,
+        krate: Crate,
+        tc: Toolchain,
+        diagnostics: Vec<DiagnosticCount>,
+    ) {
+        self.experiments
+            .entry(ex.name.to_string())
+            .or_insert_with(DummyData::default)
+            .diagnostics
+            .insert((krate, tc), diagnostics);
+    }
+
+    pub fn add_dummy_future_incompats(
+        &mut self,
+        ex: &Experiment,
+        krate: Crate,
+        tc: Toolchain,
+        future_incompats: Vec<FutureIncompat>,
+    ) {
+        self.experiments
+            .entry(ex.name.to_string())
+            .or_insert_with(DummyData::default)
+            .future_incompats
+            .insert((krate, tc), future_incompats);
+    }
+
+    pub fn add_dummy_unstable_features(
+        &mut self,
+        ex: &Experiment,
+        krate: Crate,
+        tc: Toolchain,
+        features: Vec<UnstableFeature>,
+    ) {
+        self.experiments
+            .entry(ex.name.to_string())
+            .or_insert_with(DummyData::default)
+            .unstable_features
+            .insert((krate, tc), features);
+    }
 }
 
 impl ReadResults for DummyDB {
-    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<GitHubRepo, String>> {
+    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<Crate, String>> {
         Ok(self.get_data(ex)?.shas.clone())
     }
 
@@ -86,4 +164,74 @@ impl ReadResults for DummyDB {
             .get(&(krate.clone(), toolchain.clone()))
             .cloned())
     }
+
+    fn load_attempts(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<u32> {
+        Ok(self
+            .get_data(ex)?
+            .attempts
+            .get(&(krate.clone(), toolchain.clone()))
+            .cloned()
+            .unwrap_or(1))
+    }
+
+    fn load_metrics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<StepMetrics>> {
+        Ok(self
+            .get_data(ex)?
+            .metrics
+            .get(&(krate.clone(), toolchain.clone()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn load_diagnostics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<DiagnosticCount>> {
+        Ok(self
+            .get_data(ex)?
+            .diagnostics
+            .get(&(krate.clone(), toolchain.clone()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn load_future_incompats(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<FutureIncompat>> {
+        Ok(self
+            .get_data(ex)?
+            .future_incompats
+            .get(&(krate.clone(), toolchain.clone()))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn load_unstable_features(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<UnstableFeature>> {
+        Ok(self
+            .get_data(ex)?
+            .unstable_features
+            .get(&(krate.clone(), toolchain.clone()))
+            .cloned()
+            .unwrap_or_default())
+    }
 }
@@ -2,7 +2,7 @@ mod db;
 #[cfg(test)]
 mod dummy;
 use crate::config::Config;
-use crate::crates::{Crate, GitHubRepo};
+use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
 pub use crate::results::db::{DatabaseDB, ProgressData};
@@ -17,7 +17,7 @@ use std::collections::HashMap;
 use std::{fmt, io::Read, io::Write, str::FromStr};
 
 pub trait ReadResults {
-    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<GitHubRepo, String>>;
+    fn load_all_shas(&self, ex: &Experiment) -> Fallible<HashMap<Crate, String>>;
     fn load_log(
         &self,
         ex: &Experiment,
@@ -30,6 +30,32 @@ pub trait ReadResults {
         toolchain: &Toolchain,
         krate: &Crate,
     ) -> Fallible<Option<TestResult>>;
+    fn load_attempts(&self, ex: &Experiment, toolchain: &Toolchain, krate: &Crate)
+        -> Fallible<u32>;
+    fn load_metrics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<StepMetrics>>;
+    fn load_diagnostics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<DiagnosticCount>>;
+    fn load_future_incompats(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<FutureIncompat>>;
+    fn load_unstable_features(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+    ) -> Fallible<Vec<UnstableFeature>>;
 }
 
 pub trait WriteResults {
@@ -39,7 +65,44 @@ pub trait WriteResults {
         toolchain: &Toolchain,
         krate: &Crate,
     ) -> Fallible<Option<TestResult>>;
-    fn record_sha(&self, ex: &Experiment, repo: &GitHubRepo, sha: &str) -> Fallible<()>;
+    /// The sha a git crate is pinned to in the experiment, if it was fetched before.
+    fn get_sha(&self, ex: &Experiment, krate: &Crate) -> Fallible<Option<String>>;
+    fn record_sha(&self, ex: &Experiment, krate: &Crate, sha: &str) -> Fallible<()>;
+    fn record_attempts(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        attempts: u32,
+    ) -> Fallible<()>;
+    fn record_metrics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        metrics: &[StepMetrics],
+    ) -> Fallible<()>;
+    fn record_diagnostics(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        diagnostics: &[DiagnosticCount],
+    ) -> Fallible<()>;
+    fn record_future_incompats(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        future_incompats: &[FutureIncompat],
+    ) -> Fallible<()>;
+    fn record_unstable_features(
+        &self,
+        ex: &Experiment,
+        toolchain: &Toolchain,
+        krate: &Crate,
+        features: &[UnstableFeature],
+    ) -> Fallible<()>;
     fn record_result<F>(
         &self,
         ex: &Experiment,
@@ -59,6 +122,55 @@ pub trait DeleteResults {
     fn delete_result(&self, ex: &Experiment, toolchain: &Toolchain, krate: &Crate) -> Fallible<()>;
 }
 
+string_enum!(pub enum Step {
+    Prepare => "prepare",
+    Build => "build",
+    Test => "test",
+});
+
+/// The resources used by one of the steps of a build.
+///
+/// The memory usage isn't recorded, as rustwide doesn't report it for its containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepMetrics {
+    pub step: Step,
+    /// Wall-clock duration of the step, in milliseconds.
+    pub duration: u64,
+    /// Size of the target directory at the end of the step, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_dir_size: Option<u64>,
+}
+
+string_enum!(pub enum DiagnosticLevel {
+    Warning => "warning",
+    Error => "error",
+});
+
+/// How many times a lint or an error was emitted by the compiler while building a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticCount {
+    /// The name of the lint, or the code of the error.
+    pub code: String,
+    pub level: DiagnosticLevel,
+    pub count: u32,
+}
+
+/// A lint that will become a hard error in a future release, triggered while building a crate.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FutureIncompat {
+    pub lint: String,
+    /// The package triggering the lint, either the crate itself or one of its dependencies.
+    pub package: String,
+}
+
+/// An unstable feature enabled by a crate with the `#![feature]` attribute.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UnstableFeature {
+    pub name: String,
+    /// The file enabling the feature, relative to the root of the crate.
+    pub file: String,
+}
+
 string_enum!(pub enum EncodingType {
     Plain => "plain",
     Gzip => "gzip",
@@ -170,12 +282,16 @@ string_enum!(pub enum FailureReason {
     Unknown => "unknown",
     OOM => "oom",
     Timeout => "timeout",
+    UndefinedBehavior => "ub",
+    OutOfTime => "out-of-time",
 });
 
 impl FailureReason {
     pub(crate) fn is_spurious(self) -> bool {
         match self {
-            FailureReason::Unknown => false,
+            FailureReason::Unknown
+            | FailureReason::UndefinedBehavior
+            | FailureReason::OutOfTime => false,
             FailureReason::OOM | FailureReason::Timeout => true,
         }
     }
@@ -232,6 +348,8 @@ mod tests {
             "build-fail:unknown" => BuildFail(Unknown),
             "build-fail:oom" => BuildFail(OOM),
             "test-fail:timeout" => TestFail(Timeout),
+            "test-fail:ub" => TestFail(UndefinedBehavior),
+            "test-fail:out-of-time" => TestFail(OutOfTime),
             "test-pass" => TestPass,
             "error" => Error,
         }
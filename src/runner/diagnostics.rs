@@ -0,0 +1,236 @@
+use crate::prelude::*;
+use crate::results::{DiagnosticCount, DiagnosticLevel, FutureIncompat};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+lazy_static! {
+    static ref FUTURE_INCOMPAT_LINT_RE: Regex =
+        Regex::new(r"`#\[(?:warn|deny|forbid)\(([a-z0-9_:]+)\)\]`").unwrap();
+}
+
+/// The version of the format of the future-incompatibility reports understood by Crater.
+const FUTURE_INCOMPAT_REPORT_VERSION: u32 = 0;
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    code: Option<DiagnosticCode>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DiagnosticCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct FutureIncompatReports {
+    version: u32,
+    reports: Vec<FutureIncompatReport>,
+}
+
+#[derive(Deserialize)]
+struct FutureIncompatReport {
+    per_package: BTreeMap<String, String>,
+}
+
+/// The diagnostics emitted by the compiler while building a crate, collected from the JSON
+/// messages of Cargo.
+#[derive(Default)]
+pub(super) struct Diagnostics {
+    seen: HashSet<(String, DiagnosticLevel, String)>,
+    counts: HashMap<(String, DiagnosticLevel), u32>,
+}
+
+impl Diagnostics {
+    /// Collect the diagnostic from a line of the output of a Cargo invocation, returning the text
+    /// to show in the build log: the rendered diagnostic for the JSON messages of the compiler,
+    /// nothing for the other JSON messages, and the line itself otherwise.
+    ///
+    /// Diagnostics emitted again by later invocations, for example by `cargo test` after
+    /// `cargo build`, are counted only once.
+    pub(super) fn parse_line(&mut self, line: &str) -> Option<String> {
+        if !line.starts_with('{') {
+            return Some(line.to_string());
+        }
+        let message = match serde_json::from_str::<CargoMessage>(line) {
+            Ok(CargoMessage {
+                reason,
+                message: Some(message),
+            }) if reason == "compiler-message" => message,
+            Ok(_) => return None,
+            Err(_) => return Some(line.to_string()),
+        };
+
+        let level = match message.level.as_str() {
+            "warning" => Some(DiagnosticLevel::Warning),
+            "error" => Some(DiagnosticLevel::Error),
+            _ => None,
+        };
+        // Diagnostics without a code, like the summaries of the warnings, aren't interesting
+        if let (Some(level), Some(code)) = (level, message.code) {
+            let rendered = message.rendered.clone().unwrap_or_default();
+            if self.seen.insert((code.code.clone(), level, rendered)) {
+                *self.counts.entry((code.code, level)).or_insert(0) += 1;
+            }
+        }
+
+        message.rendered
+    }
+
+    pub(super) fn counts(&self) -> Vec<DiagnosticCount> {
+        let mut counts = self
+            .counts
+            .iter()
+            .map(|((code, level), count)| DiagnosticCount {
+                code: code.clone(),
+                level: *level,
+                count: *count,
+            })
+            .collect::<Vec<_>>();
+        counts.sort_by(|a, b| (&a.code, a.level.to_str()).cmp(&(&b.code, b.level.to_str())));
+        counts
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.seen.clear();
+        self.counts.clear();
+    }
+}
+
+/// Parse the report Cargo writes in the target directory, which contains the rendered diagnostics
+/// of the lints each package triggers. Reports in an unknown format are rejected, as the lints
+/// can't be reliably extracted from them.
+pub(super) fn parse_future_incompat_report(json: &str) -> Fallible<Vec<FutureIncompat>> {
+    let reports: FutureIncompatReports = serde_json::from_str(json)?;
+    if reports.version != FUTURE_INCOMPAT_REPORT_VERSION {
+        bail!(
+            "unsupported version of the future-incompatibility report: {}",
+            reports.version
+        );
+    }
+
+    let mut future_incompats = BTreeSet::new();
+    for report in reports.reports {
+        for (package, rendered) in report.per_package {
+            for captures in FUTURE_INCOMPAT_LINT_RE.captures_iter(&rendered) {
+                future_incompats.insert(FutureIncompat {
+                    lint: captures[1].to_string(),
+                    package: package.clone(),
+                });
+            }
+        }
+    }
+    Ok(future_incompats.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_future_incompat_report, Diagnostics};
+    use crate::results::{DiagnosticCount, DiagnosticLevel, FutureIncompat};
+
+    #[test]
+    fn test_parse_line() {
+        // Messages emitted by `cargo build --message-format=json`, whose rendered diagnostics
+        // don't contain any color escape codes
+        let unused = r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///opt/rustwide/workdir)","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/opt/rustwide/workdir/src/lib.rs","edition":"2018","doctest":true},"message":{"rendered":"warning: unused variable: `x`\n --> src/lib.rs:2:9\n  |\n2 |     let x = 1;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_x`\n  |\n  = note: `#[warn(unused_variables)]` on by default\n\n","children":[{"children":[],"code":null,"level":"note","message":"`#[warn(unused_variables)]` on by default","rendered":null,"spans":[]}],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `x`","spans":[]}}"#;
+        let output = [
+            "   Compiling foo v0.1.0 (/opt/rustwide/workdir)",
+            unused,
+            r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///opt/rustwide/workdir)","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/opt/rustwide/workdir/src/lib.rs","edition":"2018","doctest":true},"message":{"rendered":"warning: unused variable: `y`\n --> src/lib.rs:3:9\n  |\n3 |     let y = 2;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_y`\n\n","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `y`","spans":[]}}"#,
+            r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///opt/rustwide/workdir)","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/opt/rustwide/workdir/src/lib.rs","edition":"2018","doctest":true},"message":{"rendered":"error[E0308]: mismatched types\n --> src/lib.rs:4:18\n  |\n4 |     let z: u32 = \"z\";\n  |            ---   ^^^ expected `u32`, found `&str`\n  |            |\n  |            expected due to this\n\n","children":[],"code":{"code":"E0308","explanation":"Expected type did not match the received type.\n"},"level":"error","message":"mismatched types","spans":[]}}"#,
+            r#"{"reason":"compiler-message","package_id":"foo 0.1.0 (path+file:///opt/rustwide/workdir)","target":{"kind":["lib"],"crate_types":["lib"],"name":"foo","src_path":"/opt/rustwide/workdir/src/lib.rs","edition":"2018","doctest":true},"message":{"rendered":"warning: 2 warnings emitted\n\n","children":[],"code":null,"level":"warning","message":"2 warnings emitted","spans":[]}}"#,
+            r#"{"reason":"build-finished","success":false}"#,
+        ];
+
+        let mut diagnostics = Diagnostics::default();
+        let logged = output
+            .iter()
+            .filter_map(|line| diagnostics.parse_line(line))
+            .collect::<Vec<_>>();
+        // The same warning emitted by another invocation is not counted twice
+        diagnostics.parse_line(unused);
+
+        // Only the rendered diagnostics are logged, instead of the JSON messages
+        assert!(logged.iter().all(|text| !text.contains('\u{1b}')));
+        assert_eq!(
+            logged,
+            vec![
+                "   Compiling foo v0.1.0 (/opt/rustwide/workdir)",
+                "warning: unused variable: `x`\n --> src/lib.rs:2:9\n  |\n2 |     let x = 1;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_x`\n  |\n  = note: `#[warn(unused_variables)]` on by default\n\n",
+                "warning: unused variable: `y`\n --> src/lib.rs:3:9\n  |\n3 |     let y = 2;\n  |         ^ help: if this is intentional, prefix it with an underscore: `_y`\n\n",
+                "error[E0308]: mismatched types\n --> src/lib.rs:4:18\n  |\n4 |     let z: u32 = \"z\";\n  |            ---   ^^^ expected `u32`, found `&str`\n  |            |\n  |            expected due to this\n\n",
+                "warning: 2 warnings emitted\n\n",
+            ]
+        );
+
+        assert_eq!(
+            diagnostics.counts(),
+            vec![
+                DiagnosticCount {
+                    code: "E0308".into(),
+                    level: DiagnosticLevel::Error,
+                    count: 1,
+                },
+                DiagnosticCount {
+                    code: "unused_variables".into(),
+                    level: DiagnosticLevel::Warning,
+                    count: 2,
+                },
+            ]
+        );
+
+        diagnostics.clear();
+        assert!(diagnostics.counts().is_empty());
+    }
+
+    #[test]
+    fn test_parse_future_incompat_report() {
+        let json = r#"{
+            "version": 0,
+            "next_id": 3,
+            "reports": [
+                {
+                    "id": 1,
+                    "suggestion_message": "",
+                    "per_package": {
+                        "rental v0.5.5": "warning: using `procedural-masquerade` crate\n  |\n  = note: `#[warn(proc_macro_back_compat)]` on by default\nwarning: using `procedural-masquerade` crate\n  = note: `#[warn(proc_macro_back_compat)]` on by default\n"
+                    }
+                },
+                {
+                    "id": 2,
+                    "suggestion_message": "",
+                    "per_package": {
+                        "foo v0.1.0 (/opt/rustwide/workdir)": "warning: trailing semicolon in macro used in expression position\n  = note: `#[deny(semicolon_in_expressions_from_macros)]` on by default\n",
+                        "rental v0.5.5": "warning: using `procedural-masquerade` crate\n  = note: `#[warn(proc_macro_back_compat)]` on by default\n"
+                    }
+                }
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_future_incompat_report(json).unwrap(),
+            vec![
+                FutureIncompat {
+                    lint: "semicolon_in_expressions_from_macros".into(),
+                    package: "foo v0.1.0 (/opt/rustwide/workdir)".into(),
+                },
+                FutureIncompat {
+                    lint: "proc_macro_back_compat".into(),
+                    package: "rental v0.5.5".into(),
+                },
+            ]
+        );
+        assert!(parse_future_incompat_report("not json").is_err());
+
+        // Reports in a newer format are rejected instead of being parsed wrongly
+        let err = parse_future_incompat_report(r#"{"version": 1, "reports": []}"#).unwrap_err();
+        assert!(err.to_string().contains("unsupported version"));
+    }
+}
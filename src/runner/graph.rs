@@ -259,11 +259,16 @@ pub(super) fn build_graph(ex: &Experiment, crates: &[Crate], config: &Config) ->
 
         let quiet = config.is_quiet(krate);
         let mut builds = Vec::new();
-        for tc in &ex.toolchains {
+        for tc in &ex.all_toolchains() {
             let build_id = graph.add_task(
                 Task {
                     krate: krate.clone(),
                     step: match ex.mode {
+                        // Other targets are only checked, as the crates can't be run there
+                        _ if tc.target.is_some() => TaskStep::CheckOnly {
+                            tc: tc.clone(),
+                            quiet,
+                        },
                         Mode::BuildOnly => TaskStep::BuildOnly {
                             tc: tc.clone(),
                             quiet,
@@ -284,6 +289,22 @@ pub(super) fn build_graph(ex: &Experiment, crates: &[Crate], config: &Config) ->
                             tc: tc.clone(),
                             quiet,
                         },
+                        Mode::Doctest
+                            if !ex.ignore_blacklist && config.should_skip_tests(krate) =>
+                        {
+                            TaskStep::BuildOnly {
+                                tc: tc.clone(),
+                                quiet,
+                            }
+                        }
+                        Mode::Doctest => TaskStep::Doctest {
+                            tc: tc.clone(),
+                            quiet,
+                        },
+                        Mode::BenchCheck => TaskStep::BenchCheck {
+                            tc: tc.clone(),
+                            quiet,
+                        },
                         Mode::Clippy => TaskStep::Clippy {
                             tc: tc.clone(),
                             quiet,
@@ -292,6 +313,18 @@ pub(super) fn build_graph(ex: &Experiment, crates: &[Crate], config: &Config) ->
                             tc: tc.clone(),
                             quiet,
                         },
+                        Mode::Miri => TaskStep::Miri {
+                            tc: tc.clone(),
+                            quiet,
+                        },
+                        Mode::Rustfmt => TaskStep::Rustfmt {
+                            tc: tc.clone(),
+                            quiet,
+                        },
+                        Mode::RustfmtIdempotence => TaskStep::RustfmtIdempotence {
+                            tc: tc.clone(),
+                            quiet,
+                        },
                         Mode::UnstableFeatures => TaskStep::UnstableFeatures { tc: tc.clone() },
                     },
                 },
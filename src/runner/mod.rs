@@ -1,3 +1,4 @@
+mod diagnostics;
 mod graph;
 mod tasks;
 mod test;
@@ -83,6 +84,19 @@ fn run_ex_inner<DB: WriteResults + Sync>(
         if ex.mode == Mode::Clippy {
             tc.add_component(workspace, "clippy")?;
         }
+        if ex.mode == Mode::Miri {
+            tc.add_component(workspace, "miri")?;
+            tc.add_component(workspace, "rust-src")?;
+            test::setup_miri(workspace, tc)?;
+        }
+        if ex.mode == Mode::Rustfmt || ex.mode == Mode::RustfmtIdempotence {
+            tc.add_component(workspace, "rustfmt")?;
+        }
+    }
+    for tc in &ex.all_toolchains() {
+        if let Some(target) = &tc.target {
+            tc.add_target(workspace, target)?;
+        }
     }
 
     info!("running tasks in {} threads...", threads_count);
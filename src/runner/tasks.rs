@@ -2,11 +2,15 @@ use crate::config::Config;
 use crate::crates::Crate;
 use crate::experiments::Experiment;
 use crate::prelude::*;
-use crate::results::{EncodingType, TestResult, WriteResults};
+use crate::results::{
+    EncodingType, FutureIncompat, StepMetrics, TestResult, UnstableFeature, WriteResults,
+};
+use crate::runner::diagnostics::Diagnostics;
 use crate::runner::{test, RunnerState};
 use crate::toolchain::Toolchain;
 use crate::utils;
-use rustwide::{BuildDirectory, Workspace};
+use rustwide::{Build, BuildDirectory, Workspace};
+use std::cell::RefCell;
 use std::sync::Mutex;
 
 use rustwide::logging::{self, LogStorage};
@@ -21,6 +25,14 @@ pub(super) struct TaskCtx<'ctx, DB: WriteResults + 'ctx> {
     pub(super) krate: &'ctx Crate,
     pub(super) state: &'ctx RunnerState,
     pub(super) quiet: bool,
+    /// The resources used by the steps of the current attempt.
+    pub(super) metrics: RefCell<Vec<StepMetrics>>,
+    /// The compiler diagnostics emitted during the current attempt.
+    pub(super) diagnostics: RefCell<Diagnostics>,
+    /// The future-incompatibility lints triggered during the current attempt.
+    pub(super) future_incompats: RefCell<Vec<FutureIncompat>>,
+    /// The unstable features enabled by the crate, found during the current attempt.
+    pub(super) unstable_features: RefCell<Vec<UnstableFeature>>,
 }
 
 impl<'ctx, DB: WriteResults + 'ctx> TaskCtx<'ctx, DB> {
@@ -43,18 +55,29 @@ impl<'ctx, DB: WriteResults + 'ctx> TaskCtx<'ctx, DB> {
             krate,
             state,
             quiet,
+            metrics: RefCell::new(Vec::new()),
+            diagnostics: RefCell::new(Diagnostics::default()),
+            future_incompats: RefCell::new(Vec::new()),
+            unstable_features: RefCell::new(Vec::new()),
         }
     }
 }
 
+type TestFn<DB> = fn(&TaskCtx<DB>, &Build) -> Fallible<TestResult>;
+
 pub(super) enum TaskStep {
     Prepare,
     Cleanup,
     BuildAndTest { tc: Toolchain, quiet: bool },
     BuildOnly { tc: Toolchain, quiet: bool },
     CheckOnly { tc: Toolchain, quiet: bool },
+    Doctest { tc: Toolchain, quiet: bool },
+    BenchCheck { tc: Toolchain, quiet: bool },
     Clippy { tc: Toolchain, quiet: bool },
     Rustdoc { tc: Toolchain, quiet: bool },
+    Miri { tc: Toolchain, quiet: bool },
+    Rustfmt { tc: Toolchain, quiet: bool },
+    RustfmtIdempotence { tc: Toolchain, quiet: bool },
     UnstableFeatures { tc: Toolchain },
 }
 
@@ -66,8 +89,15 @@ impl fmt::Debug for TaskStep {
             TaskStep::BuildAndTest { ref tc, quiet } => ("build and test", quiet, Some(tc)),
             TaskStep::BuildOnly { ref tc, quiet } => ("build", quiet, Some(tc)),
             TaskStep::CheckOnly { ref tc, quiet } => ("check", quiet, Some(tc)),
+            TaskStep::Doctest { ref tc, quiet } => ("doctest", quiet, Some(tc)),
+            TaskStep::BenchCheck { ref tc, quiet } => ("bench check", quiet, Some(tc)),
             TaskStep::Clippy { ref tc, quiet } => ("clippy", quiet, Some(tc)),
             TaskStep::Rustdoc { ref tc, quiet } => ("doc", quiet, Some(tc)),
+            TaskStep::Miri { ref tc, quiet } => ("miri", quiet, Some(tc)),
+            TaskStep::Rustfmt { ref tc, quiet } => ("rustfmt", quiet, Some(tc)),
+            TaskStep::RustfmtIdempotence { ref tc, quiet } => {
+                ("rustfmt idempotence", quiet, Some(tc))
+            }
             TaskStep::UnstableFeatures { ref tc } => ("find unstable features on", false, Some(tc)),
         };
 
@@ -107,8 +137,13 @@ impl Task {
             TaskStep::BuildAndTest { ref tc, .. }
             | TaskStep::BuildOnly { ref tc, .. }
             | TaskStep::CheckOnly { ref tc, .. }
+            | TaskStep::Doctest { ref tc, .. }
+            | TaskStep::BenchCheck { ref tc, .. }
             | TaskStep::Clippy { ref tc, .. }
             | TaskStep::Rustdoc { ref tc, .. }
+            | TaskStep::Miri { ref tc, .. }
+            | TaskStep::Rustfmt { ref tc, .. }
+            | TaskStep::RustfmtIdempotence { ref tc, .. }
             | TaskStep::UnstableFeatures { ref tc } => {
                 db.get_result(ex, tc, &self.krate).unwrap_or(None).is_none()
             }
@@ -129,8 +164,13 @@ impl Task {
             TaskStep::BuildAndTest { ref tc, .. }
             | TaskStep::BuildOnly { ref tc, .. }
             | TaskStep::CheckOnly { ref tc, .. }
+            | TaskStep::Doctest { ref tc, .. }
+            | TaskStep::BenchCheck { ref tc, .. }
             | TaskStep::Clippy { ref tc, .. }
             | TaskStep::Rustdoc { ref tc, .. }
+            | TaskStep::Miri { ref tc, .. }
+            | TaskStep::Rustfmt { ref tc, .. }
+            | TaskStep::RustfmtIdempotence { ref tc, .. }
             | TaskStep::UnstableFeatures { ref tc } => {
                 let log_storage = state
                     .lock()
@@ -159,12 +199,17 @@ impl Task {
     pub(super) fn run<'ctx, 's: 'ctx, DB: WriteResults>(
         &'s self,
         config: &'ctx Config,
-        workspace: &Workspace,
+        workspace: &'ctx Workspace,
         build_dir: &'ctx Mutex<BuildDirectory>,
         ex: &'ctx Experiment,
         db: &'ctx DB,
         state: &'ctx RunnerState,
     ) -> Fallible<()> {
+        let run_test = |tc, quiet, action, test_fn: TestFn<DB>| {
+            let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
+            test::run_test(action, &ctx, test_fn)
+        };
+
         match self.step {
             TaskStep::Cleanup => {
                 // Remove stored logs
@@ -177,49 +222,69 @@ impl Task {
                     .prepare_logs
                     .insert(self.krate.clone(), storage.clone());
                 logging::capture(&storage, || {
-                    let rustwide_crate = self.krate.to_rustwide();
-                    rustwide_crate.fetch(workspace)?;
+                    if let Crate::AltRegistry(krate) = &self.krate {
+                        krate.fetch(config)?;
+                    }
+
+                    if let Some(repo) = self.krate.git_repo() {
+                        // Check out the commit recorded by a previous run or given in the list of
+                        // crates, so that retries and reruns test the same code
+                        let pinned = db.get_sha(ex, &self.krate)?;
+                        let sha = match pinned {
+                            Some(ref sha) => repo.fetch_rev(Some(sha.as_str()))?,
+                            None => repo.fetch()?,
+                        };
 
-                    if let Crate::GitHub(repo) = &self.krate {
-                        if let Some(sha) = rustwide_crate.git_commit(workspace) {
-                            db.record_sha(ex, repo, &sha).with_context(|_| {
-                                format!("failed to record the sha of GitHub repo {}", repo.slug())
+                        if pinned.as_ref() != Some(&sha) {
+                            db.record_sha(ex, &self.krate, &sha).with_context(|_| {
+                                format!("failed to record the sha of {}", self.krate)
                             })?;
-                        } else {
-                            bail!("unable to capture sha for {}", repo.slug());
                         }
                     }
+
+                    self.krate.to_rustwide().fetch(workspace)?;
                     Ok(())
                 })?;
             }
             TaskStep::BuildAndTest { ref tc, quiet } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
-                test::run_test("testing", &ctx, test::test_build_and_test)?;
+                run_test(tc, quiet, "testing", test::test_build_and_test)?
             }
             TaskStep::BuildOnly { ref tc, quiet } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
-                test::run_test("building", &ctx, test::test_build_only)?;
+                run_test(tc, quiet, "building", test::test_build_only)?
             }
             TaskStep::CheckOnly { ref tc, quiet } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
-                test::run_test("checking", &ctx, test::test_check_only)?;
+                run_test(tc, quiet, "checking", test::test_check_only)?
+            }
+            TaskStep::Doctest { ref tc, quiet } => {
+                run_test(tc, quiet, "testing docs of", test::test_doctests)?
+            }
+            TaskStep::BenchCheck { ref tc, quiet } => {
+                run_test(tc, quiet, "building benchmarks of", test::test_bench_check)?
             }
             TaskStep::Clippy { ref tc, quiet } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
-                test::run_test("linting", &ctx, test::test_clippy_only)?;
+                run_test(tc, quiet, "linting", test::test_clippy_only)?
             }
             TaskStep::Rustdoc { ref tc, quiet } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, quiet);
-                test::run_test("documenting", &ctx, test::test_rustdoc)?;
+                run_test(tc, quiet, "documenting", test::test_rustdoc)?
             }
-            TaskStep::UnstableFeatures { ref tc } => {
-                let ctx = TaskCtx::new(build_dir, config, db, ex, tc, &self.krate, state, false);
-                test::run_test(
-                    "checking unstable",
-                    &ctx,
-                    crate::runner::unstable_features::find_unstable_features,
-                )?;
+            TaskStep::Miri { ref tc, quiet } => {
+                run_test(tc, quiet, "interpreting", test::test_miri)?
+            }
+            TaskStep::Rustfmt { ref tc, quiet } => {
+                run_test(tc, quiet, "checking formatting of", test::test_rustfmt)?
             }
+            TaskStep::RustfmtIdempotence { ref tc, quiet } => run_test(
+                tc,
+                quiet,
+                "checking formatting of",
+                test::test_rustfmt_idempotence,
+            )?,
+            TaskStep::UnstableFeatures { ref tc } => run_test(
+                tc,
+                false,
+                "checking unstable",
+                crate::runner::unstable_features::find_unstable_features,
+            )?,
         }
 
         Ok(())
@@ -1,11 +1,31 @@
+use crate::dirs::WORK_DIR;
+use crate::experiments::Mode;
 use crate::prelude::*;
-use crate::results::{BrokenReason, EncodingType, FailureReason, TestResult, WriteResults};
+use crate::results::{
+    BrokenReason, EncodingType, FailureReason, Step, StepMetrics, TestResult, WriteResults,
+};
+use crate::runner::diagnostics::parse_future_incompat_report;
 use crate::runner::tasks::TaskCtx;
 use crate::runner::OverrideResult;
+use crate::toolchain::Toolchain;
 use failure::Error;
+use log::LevelFilter;
 use remove_dir_all::remove_dir_all;
-use rustwide::cmd::{CommandError, SandboxBuilder};
-use rustwide::{Build, PrepareError};
+use rustwide::cmd::{Command, CommandError, SandboxBuilder};
+use rustwide::logging::{self, LogStorage};
+use rustwide::{Build, PrepareError, Workspace};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// Where the cargo home of the workspace is mounted inside the sandbox.
+const SANDBOX_CARGO_HOME: &str = "/opt/rustwide/cargo-home";
+/// Where the target directory of the build is mounted inside the sandbox.
+const SANDBOX_TARGET_DIR: &str = "/opt/rustwide/target";
+/// The report of the future-incompatibility lints Cargo writes in the target directory.
+const FUTURE_INCOMPAT_REPORT: &str = ".future-incompat-report.json";
+/// The writable copy of the source formatted when testing the idempotence of rustfmt.
+const RUSTFMT_COPY: &str = "rustfmt-idempotence";
 
 fn failure_reason(err: &Error) -> FailureReason {
     for cause in err.iter_chain() {
@@ -49,10 +69,31 @@ fn detect_broken<T>(res: Result<T, Error>) -> Result<T, Error> {
     }
 }
 
+/// Settings of a Cargo invocation needed only by some of the experiment modes.
+#[derive(Default)]
+struct CargoOptions<'a> {
+    /// The toolchain running Cargo, instead of the one being tested.
+    toolchain: Option<&'a Toolchain>,
+    /// The limit on the duration of the whole invocation, in addition to the limit on the time
+    /// without any output.
+    timeout: Option<Duration>,
+    /// Additional environment variables to set for the invocation.
+    env: Vec<(&'static str, String)>,
+}
+
 fn run_cargo<DB: WriteResults>(
     ctx: &TaskCtx<DB>,
     build_env: &Build,
     args: &[&str],
+) -> Fallible<()> {
+    run_cargo_with_options(ctx, build_env, args, &CargoOptions::default())
+}
+
+fn run_cargo_with_options<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+    args: &[&str],
+    options: &CargoOptions<'_>,
 ) -> Fallible<()> {
     let mut rustflags = format!("--cap-lints={}", ctx.experiment.cap_lints.to_str());
     if let Some(ref tc_rustflags) = ctx.toolchain.rustflags {
@@ -66,20 +107,155 @@ fn run_cargo<DB: WriteResults>(
         "RUSTFLAGS"
     };
 
-    let mut command = build_env
-        .cargo()
-        .args(args)
+    // The diagnostics of the commands invoking the compiler are parsed from its JSON messages
+    let json_messages = match args.get(0) {
+        Some(&"build") | Some(&"check") | Some(&"test") | Some(&"bench") | Some(&"clippy")
+        | Some(&"doc") => true,
+        _ => false,
+    };
+
+    let mut args = args.to_vec();
+    // Formatting doesn't depend on the features or the target, and rustfmt rejects their flags
+    if args.get(0) != Some(&"fmt") {
+        args.extend(ctx.toolchain.features.cargo_args());
+        if let Some(ref target) = ctx.toolchain.target {
+            args.push("--target");
+            args.push(target);
+        }
+    }
+    if json_messages {
+        args.push("--message-format=json");
+    }
+
+    let command = match options.toolchain {
+        Some(toolchain) => build_env.cmd(toolchain.cargo()),
+        None => build_env.cargo(),
+    };
+    let mut command = command
+        .args(&args)
         .env("CARGO_INCREMENTAL", "0")
         .env("RUST_BACKTRACE", "full")
         .env(rustflags_env, rustflags);
     if ctx.quiet {
         command = command.no_output_timeout(None);
     }
-    command.run()?;
+    if let Some(timeout) = options.timeout {
+        command = command.timeout(Some(timeout));
+    }
+    for (key, value) in &options.env {
+        command = command.env(*key, value);
+    }
+
+    if json_messages {
+        // The JSON messages are only parsed, and their rendered diagnostics logged in their place
+        let mut diagnostics = ctx.diagnostics.borrow_mut();
+        let mut process_line = |line: &str| {
+            if let Some(text) = diagnostics.parse_line(line) {
+                for line in text.lines() {
+                    info!("{}", line);
+                }
+            }
+        };
+        command
+            .log_output(false)
+            .process_lines(&mut process_line)
+            .run()?;
+    } else {
+        command.run()?;
+    }
+
+    Ok(())
+}
+
+/// Run cargo while capturing its output a second time, without the size limits of the build logs,
+/// to find out why it failed.
+fn run_cargo_captured<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+    args: &[&str],
+    options: &CargoOptions<'_>,
+) -> (Fallible<()>, String) {
+    let storage = LogStorage::new(LevelFilter::Info);
+    let res = logging::capture(&storage, || {
+        run_cargo_with_options(ctx, build_env, args, options)
+    });
+    (res, storage.to_string())
+}
+
+fn record_metrics<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+    step: Step,
+    duration: Duration,
+) {
+    ctx.metrics.borrow_mut().push(StepMetrics {
+        step,
+        duration: duration.as_millis() as u64,
+        target_dir_size: crate::utils::path::dir_size(&build_env.host_target_dir()).ok(),
+    });
+}
+
+/// Run a step of the build, recording the resources it used.
+fn measure<DB: WriteResults, T>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+    step: Step,
+    f: impl FnOnce() -> T,
+) -> T {
+    let start = Instant::now();
+    let res = f();
+    record_metrics(ctx, build_env, step, start.elapsed());
+    res
+}
 
+/// Run the build of the crate, and then run it again from scratch to record more samples of its
+/// duration. The diagnostics of the repeated builds are the same, so they're only counted once.
+fn measure_build<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+    f: impl Fn() -> Fallible<()>,
+) -> Fallible<()> {
+    measure(ctx, build_env, Step::Build, &f)?;
+    for _ in 1..ctx.config.sandbox.build_samples {
+        build_env.cargo().args(&["clean"]).run()?;
+        measure(ctx, build_env, Step::Build, &f)?;
+    }
     Ok(())
 }
 
+/// Collect the lints that will become hard errors in a future release, from the report Cargo
+/// saves in the target directory at the end of each build.
+fn collect_future_incompats<DB: WriteResults>(ctx: &TaskCtx<DB>, build_env: &Build) {
+    // Only the modes invoking the compiler can trigger the lints
+    match ctx.experiment.mode {
+        Mode::BuildAndTest
+        | Mode::BuildOnly
+        | Mode::CheckOnly
+        | Mode::Doctest
+        | Mode::BenchCheck
+        | Mode::Clippy
+        | Mode::Rustdoc
+        | Mode::Miri => {}
+        Mode::Rustfmt | Mode::RustfmtIdempotence | Mode::UnstableFeatures => return,
+    }
+
+    // Cargo only writes the report when a lint fired, and older versions never write it
+    let path = build_env.host_target_dir().join(FUTURE_INCOMPAT_REPORT);
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    // The target directory is reused by the next builds, which must not see this report
+    if let Err(err) = std::fs::remove_file(&path) {
+        warn!("failed to remove {}: {}", path.display(), err);
+    }
+
+    match parse_future_incompat_report(&json) {
+        Ok(future_incompats) => *ctx.future_incompats.borrow_mut() = future_incompats,
+        Err(err) => warn!("failed to parse the future-incompatibility report: {}", err),
+    }
+}
+
 pub(super) fn run_test<DB: WriteResults>(
     action: &str,
     ctx: &TaskCtx<DB>,
@@ -112,15 +288,80 @@ pub(super) fn run_test<DB: WriteResults>(
                     ctx.toolchain.to_string(),
                     ctx.experiment.name
                 );
-                let sandbox = SandboxBuilder::new()
-                    .memory_limit(Some(ctx.config.sandbox.memory_limit.to_bytes()))
-                    .enable_networking(false);
-                detect_broken(ctx.build_dir.lock().unwrap().build(
-                    &ctx.toolchain,
-                    &ctx.krate.to_rustwide(),
-                    sandbox,
-                    |build| test_fn(ctx, build),
-                ))
+
+                let mut attempts = 1;
+                loop {
+                    let sandbox = SandboxBuilder::new()
+                        .memory_limit(Some(ctx.config.sandbox.memory_limit.to_bytes()))
+                        .enable_networking(false);
+                    ctx.metrics.borrow_mut().clear();
+                    ctx.diagnostics.borrow_mut().clear();
+                    ctx.future_incompats.borrow_mut().clear();
+                    ctx.unstable_features.borrow_mut().clear();
+                    let start = Instant::now();
+                    let res = detect_broken(ctx.build_dir.lock().unwrap().build(
+                        &ctx.toolchain,
+                        &ctx.krate.to_rustwide(),
+                        sandbox,
+                        |build| {
+                            // Rustwide prepares the crate before calling the closure
+                            record_metrics(ctx, build, Step::Prepare, start.elapsed());
+                            let res = test_fn(ctx, build);
+                            collect_future_incompats(ctx, build);
+                            res
+                        },
+                    ))?;
+
+                    // Spurious failures are retried before the result is recorded, keeping the
+                    // logs of all the attempts
+                    match res {
+                        TestResult::BuildFail(reason) | TestResult::TestFail(reason)
+                            if reason.is_spurious()
+                                && attempts <= ctx.config.sandbox.spurious_retries =>
+                        {
+                            warn!(
+                                "spurious failure ({}) on attempt {}, retrying {}",
+                                res, attempts, ctx.krate
+                            );
+                            attempts += 1;
+                        }
+                        _ => {
+                            if attempts > 1 {
+                                ctx.db.record_attempts(
+                                    ctx.experiment,
+                                    ctx.toolchain,
+                                    ctx.krate,
+                                    attempts,
+                                )?;
+                            }
+                            ctx.db.record_metrics(
+                                ctx.experiment,
+                                ctx.toolchain,
+                                ctx.krate,
+                                &ctx.metrics.borrow(),
+                            )?;
+                            ctx.db.record_diagnostics(
+                                ctx.experiment,
+                                ctx.toolchain,
+                                ctx.krate,
+                                &ctx.diagnostics.borrow().counts(),
+                            )?;
+                            ctx.db.record_future_incompats(
+                                ctx.experiment,
+                                ctx.toolchain,
+                                ctx.krate,
+                                &ctx.future_incompats.borrow(),
+                            )?;
+                            ctx.db.record_unstable_features(
+                                ctx.experiment,
+                                ctx.toolchain,
+                                ctx.krate,
+                                &ctx.unstable_features.borrow(),
+                            )?;
+                            return Ok(res);
+                        }
+                    }
+                }
             },
         )?;
     }
@@ -128,13 +369,17 @@ pub(super) fn run_test<DB: WriteResults>(
 }
 
 fn build<DB: WriteResults>(ctx: &TaskCtx<DB>, build_env: &Build) -> Fallible<()> {
-    run_cargo(ctx, build_env, &["build", "--frozen"])?;
-    run_cargo(ctx, build_env, &["test", "--frozen", "--no-run"])?;
-    Ok(())
+    measure_build(ctx, build_env, || {
+        run_cargo(ctx, build_env, &["build", "--frozen"])?;
+        run_cargo(ctx, build_env, &["test", "--frozen", "--no-run"])?;
+        Ok(())
+    })
 }
 
 fn test<DB: WriteResults>(ctx: &TaskCtx<DB>, build_env: &Build) -> Fallible<()> {
-    run_cargo(ctx, build_env, &["test", "--frozen"])
+    measure(ctx, build_env, Step::Test, || {
+        run_cargo(ctx, build_env, &["test", "--frozen"])
+    })
 }
 
 pub(super) fn test_build_and_test<DB: WriteResults>(
@@ -167,15 +412,59 @@ pub(super) fn test_build_only<DB: WriteResults>(
     }
 }
 
+pub(super) fn test_doctests<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+) -> Fallible<TestResult> {
+    let (res, output) = measure(ctx, build_env, Step::Build, || {
+        run_cargo_captured(
+            ctx,
+            build_env,
+            &["build", "--frozen", "--lib"],
+            &CargoOptions::default(),
+        )
+    });
+    if let Err(err) = res {
+        // Only libraries have doctests
+        if output.contains("no library targets found") {
+            return Ok(TestResult::TestSkipped);
+        }
+        return Ok(TestResult::BuildFail(failure_reason(&err)));
+    }
+
+    if let Err(err) = measure(ctx, build_env, Step::Test, || {
+        run_cargo(ctx, build_env, &["test", "--frozen", "--doc"])
+    }) {
+        Ok(TestResult::TestFail(failure_reason(&err)))
+    } else {
+        Ok(TestResult::TestPass)
+    }
+}
+
+pub(super) fn test_bench_check<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+) -> Fallible<TestResult> {
+    if let Err(err) = measure(ctx, build_env, Step::Build, || {
+        run_cargo(ctx, build_env, &["bench", "--frozen", "--no-run"])
+    }) {
+        Ok(TestResult::BuildFail(failure_reason(&err)))
+    } else {
+        Ok(TestResult::TestPass)
+    }
+}
+
 pub(super) fn test_check_only<DB: WriteResults>(
     ctx: &TaskCtx<DB>,
     build_env: &Build,
 ) -> Fallible<TestResult> {
-    if let Err(err) = run_cargo(
-        ctx,
-        build_env,
-        &["check", "--frozen", "--all", "--all-targets"],
-    ) {
+    let mut args = vec!["check", "--frozen", "--all"];
+    // Tests and benchmarks usually need the standard library, which other targets might lack
+    if ctx.toolchain.target.is_none() {
+        args.push("--all-targets");
+    }
+
+    if let Err(err) = measure_build(ctx, build_env, || run_cargo(ctx, build_env, &args)) {
         Ok(TestResult::BuildFail(failure_reason(&err)))
     } else {
         Ok(TestResult::TestPass)
@@ -186,11 +475,13 @@ pub(super) fn test_clippy_only<DB: WriteResults>(
     ctx: &TaskCtx<DB>,
     build_env: &Build,
 ) -> Fallible<TestResult> {
-    if let Err(err) = run_cargo(
-        ctx,
-        build_env,
-        &["clippy", "--frozen", "--all", "--all-targets"],
-    ) {
+    if let Err(err) = measure(ctx, build_env, Step::Build, || {
+        run_cargo(
+            ctx,
+            build_env,
+            &["clippy", "--frozen", "--all", "--all-targets"],
+        )
+    }) {
         Ok(TestResult::BuildFail(failure_reason(&err)))
     } else {
         Ok(TestResult::TestPass)
@@ -201,11 +492,13 @@ pub(super) fn test_rustdoc<DB: WriteResults>(
     ctx: &TaskCtx<DB>,
     build_env: &Build,
 ) -> Fallible<TestResult> {
-    let res = run_cargo(
-        ctx,
-        build_env,
-        &["doc", "--frozen", "--no-deps", "--document-private-items"],
-    );
+    let res = measure(ctx, build_env, Step::Build, || {
+        run_cargo(
+            ctx,
+            build_env,
+            &["doc", "--frozen", "--no-deps", "--document-private-items"],
+        )
+    });
 
     // Make sure to remove the built documentation
     // There is no point in storing it after the build is done
@@ -217,3 +510,175 @@ pub(super) fn test_rustdoc<DB: WriteResults>(
         Ok(TestResult::TestPass)
     }
 }
+
+pub(super) fn test_rustfmt<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+) -> Fallible<TestResult> {
+    if let Err(err) = measure(ctx, build_env, Step::Test, || {
+        run_cargo(ctx, build_env, &["fmt", "--all", "--", "--check"])
+    }) {
+        Ok(TestResult::TestFail(failure_reason(&err)))
+    } else {
+        Ok(TestResult::TestPass)
+    }
+}
+
+pub(super) fn test_rustfmt_idempotence<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+) -> Fallible<TestResult> {
+    // The crate is formatted with the first toolchain of the experiment, and then checked with the
+    // toolchain being tested. The source directory is read-only inside the sandbox, so the crate
+    // is formatted in a copy stored in the target directory.
+    let host_copy = build_env.host_target_dir().join(RUSTFMT_COPY);
+    if host_copy.exists() {
+        remove_dir_all(&host_copy)?;
+    }
+    copy_dir(build_env.host_source_dir(), &host_copy)?;
+    let manifest = format!(
+        "--manifest-path={}/{}/Cargo.toml",
+        SANDBOX_TARGET_DIR, RUSTFMT_COPY
+    );
+
+    let reference = CargoOptions {
+        toolchain: Some(&ctx.experiment.toolchains[0]),
+        ..CargoOptions::default()
+    };
+
+    let result = if let Err(err) = run_cargo_with_options(
+        ctx,
+        build_env,
+        &["fmt", manifest.as_str(), "--all"],
+        &reference,
+    ) {
+        TestResult::BuildFail(failure_reason(&err))
+    } else if let Err(err) = measure(ctx, build_env, Step::Test, || {
+        run_cargo(
+            ctx,
+            build_env,
+            &["fmt", manifest.as_str(), "--all", "--", "--check"],
+        )
+    }) {
+        TestResult::TestFail(failure_reason(&err))
+    } else {
+        TestResult::TestPass
+    };
+    remove_dir_all(&host_copy)?;
+
+    Ok(result)
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Fallible<()> {
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        let dest = dest.join(entry.path().strip_prefix(src)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&dest)?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+fn miri_sysroot_dir(toolchain: &Toolchain) -> String {
+    format!("miri-sysroots/{}", toolchain.to_path_component())
+}
+
+/// Build the standard library for Miri on the host, as the sandbox has no network access to fetch
+/// its dependencies. It's stored in the cargo home, which is mounted inside the sandbox.
+pub(super) fn setup_miri(workspace: &Workspace, toolchain: &Toolchain) -> Fallible<()> {
+    // The cache directory must be an absolute path to be picked up by Miri.
+    let cache = crate::utils::path::normalize_path(&WORK_DIR.join("cargo-home"))
+        .join(miri_sysroot_dir(toolchain));
+    Command::new(workspace, toolchain.cargo())
+        .args(&["miri", "setup"])
+        .env("XDG_CACHE_HOME", &cache)
+        .run()?;
+    Ok(())
+}
+
+/// Classify a failed Miri run from its output, as the exit code is the same for undefined
+/// behavior, failing tests and compilation errors.
+fn miri_failure(output: &str, err: &Error) -> TestResult {
+    // Running out of the time budget is deterministic, unlike the other timeouts, so it must not
+    // be retried as a spurious failure.
+    let out_of_time = err.iter_chain().any(|cause| match cause.downcast_ctx() {
+        Some(&CommandError::Timeout(_)) => true,
+        _ => false,
+    });
+
+    if output.contains("error: Undefined Behavior") {
+        TestResult::TestFail(FailureReason::UndefinedBehavior)
+    } else if out_of_time {
+        TestResult::TestFail(FailureReason::OutOfTime)
+    } else if output.contains("error: could not compile") {
+        TestResult::BuildFail(failure_reason(err))
+    } else {
+        TestResult::TestFail(failure_reason(err))
+    }
+}
+
+pub(super) fn test_miri<DB: WriteResults>(
+    ctx: &TaskCtx<DB>,
+    build_env: &Build,
+) -> Fallible<TestResult> {
+    // The sysroot built by `setup_miri` is in the cargo home, mounted inside the sandbox
+    let options = CargoOptions {
+        timeout: Some(Duration::from_secs(ctx.config.sandbox.miri_timeout)),
+        env: vec![(
+            "MIRI_SYSROOT",
+            format!(
+                "{}/{}/miri",
+                SANDBOX_CARGO_HOME,
+                miri_sysroot_dir(ctx.toolchain)
+            ),
+        )],
+        ..CargoOptions::default()
+    };
+    let (res, output) = measure(ctx, build_env, Step::Test, || {
+        run_cargo_captured(ctx, build_env, &["miri", "test", "--frozen"], &options)
+    });
+    if let Err(err) = res {
+        Ok(miri_failure(&output, &err))
+    } else {
+        Ok(TestResult::TestPass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::miri_failure;
+    use crate::results::{FailureReason, TestResult};
+    use failure::{err_msg, Error};
+    use rustwide::cmd::CommandError;
+
+    #[test]
+    fn test_miri_failure() {
+        let err = err_msg("command failed");
+
+        assert_eq!(
+            miri_failure(
+                "error: Undefined Behavior: dereferencing pointer failed: null pointer",
+                &err
+            ),
+            TestResult::TestFail(FailureReason::UndefinedBehavior)
+        );
+        assert_eq!(
+            miri_failure("error: could not compile `foo`.", &err),
+            TestResult::BuildFail(FailureReason::Unknown)
+        );
+        assert_eq!(
+            miri_failure("test result: FAILED. 1 passed; 1 failed", &err),
+            TestResult::TestFail(FailureReason::Unknown)
+        );
+
+        let timeout: Error = CommandError::Timeout(1800).into();
+        assert_eq!(
+            miri_failure("test foo ... ", &timeout),
+            TestResult::TestFail(FailureReason::OutOfTime)
+        );
+        assert!(!FailureReason::OutOfTime.is_spurious());
+    }
+}
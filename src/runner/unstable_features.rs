@@ -1,19 +1,19 @@
 use crate::prelude::*;
-use crate::results::TestResult;
-use crate::results::WriteResults;
+use crate::results::{TestResult, UnstableFeature, WriteResults};
 use crate::runner::tasks::TaskCtx;
 use rustwide::Build;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use std::path::Path;
 use walkdir::{DirEntry, WalkDir};
 
 pub(super) fn find_unstable_features<DB: WriteResults>(
-    _ctx: &TaskCtx<DB>,
+    ctx: &TaskCtx<DB>,
     build: &Build,
 ) -> Fallible<TestResult> {
-    let mut features = HashSet::new();
+    let source_dir = build.host_source_dir();
+    let mut features = BTreeSet::new();
 
-    for entry in WalkDir::new(build.host_source_dir())
+    for entry in WalkDir::new(&source_dir)
         .into_iter()
         .filter_entry(|e| !is_hidden(e))
     {
@@ -31,17 +31,27 @@ pub(super) fn find_unstable_features<DB: WriteResults>(
         }
 
         let new_features = parse_features(entry.path())?;
+        let file = entry
+            .path()
+            .strip_prefix(&source_dir)?
+            .to_string_lossy()
+            .replace('\\', "/");
 
         for feature in new_features {
-            features.insert(feature);
+            if !feature.is_empty() {
+                features.insert(UnstableFeature {
+                    name: feature,
+                    file: file.clone(),
+                });
+            }
         }
     }
 
-    let mut features: Vec<_> = features.into_iter().collect();
-    features.sort();
-    for feature in features {
-        info!("unstable-feature: {}", feature);
+    let features: Vec<_> = features.into_iter().collect();
+    for feature in &features {
+        info!("unstable-feature: {} (in {})", feature.name, feature.file);
     }
+    *ctx.unstable_features.borrow_mut() = features;
 
     Ok(TestResult::TestPass)
 }
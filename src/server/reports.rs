@@ -1,3 +1,5 @@
+use crate::bisect::{self, Bisection, BisectionStatus};
+use crate::crates::DependencyGraph;
 use crate::experiments::{Experiment, Status};
 use crate::prelude::*;
 use crate::report::{self, Comparison, TestResults};
@@ -24,11 +26,62 @@ fn generate_report(data: &Data, ex: &Experiment, results: &DatabaseDB) -> Fallib
     let writer = report::S3Writer::create(Box::new(client), dest.parse()?)?;
 
     let crates = ex.get_crates(&data.db)?;
-    let res = report::gen(results, &ex, &crates, &writer, &data.config)?;
+    let deps = DependencyGraph::load(&data.db)?;
+    let res = report::gen(results, &ex, &crates, &writer, &data.config, &deps)?;
 
     Ok(res)
 }
 
+fn finish_probe(data: &Data, probe: &mut Experiment, parent: &Experiment) -> Fallible<()> {
+    info!(
+        "recording the results of the bisection probe {}...",
+        probe.name
+    );
+    bisect::record_probe_results(&data.db, &data.config, probe, parent)?;
+
+    // Probes stopped by an error on the agent are missing some results
+    let (completed, all) = probe.raw_progress(&data.db)?;
+    if completed == all {
+        probe.set_status(&data.db, Status::Completed)?;
+    } else {
+        probe.set_status(&data.db, Status::Failed)?;
+    }
+
+    // The next round can only start once all the probes of the current one are finished
+    if bisect::has_pending_probes(&data.db, parent)? || bisect::queue_probes(&data.db, parent)? {
+        return Ok(());
+    }
+
+    info!("bisections of the experiment {} completed", parent.name);
+    if let Some(ref github_issue) = parent.github_issue {
+        let mut message = Message::new().line(
+            "mag",
+            format!(
+                "Bisection of the regressions found by **`{}`** is completed!",
+                parent.name
+            ),
+        );
+        for bisection in Bisection::load_all(&data.db, parent)? {
+            message = match bisection.status {
+                BisectionStatus::Completed => message.line(
+                    "boom",
+                    format!(
+                        "`{}` regressed between `{}` and `{}`.",
+                        bisection.krate, bisection.last_good, bisection.first_bad
+                    ),
+                ),
+                _ => message.line(
+                    "grey_question",
+                    format!("`{}` couldn't be bisected.", bisection.krate),
+                ),
+            };
+        }
+        message.send(&github_issue.api_url, data)?;
+    }
+
+    Ok(())
+}
+
 fn reports_thread(data: &Data, wakes: &mpsc::Receiver<()>) -> Fallible<()> {
     let timeout = Duration::from_secs(AUTOMATIC_THREAD_WAKEUP);
     let results = DatabaseDB::new(&data.db);
@@ -45,6 +98,13 @@ fn reports_thread(data: &Data, wakes: &mpsc::Receiver<()>) -> Fallible<()> {
                 continue;
             }
         };
+
+        // Probes of bisections have no report, their results only advance the bisections
+        if let Some(parent) = bisect::probe_parent(&data.db, &ex)? {
+            finish_probe(data, &mut ex, &parent)?;
+            continue;
+        }
+
         let name = ex.name.clone();
 
         info!("generating report for experiment {}...", name);
@@ -87,27 +147,44 @@ fn reports_thread(data: &Data, wakes: &mpsc::Receiver<()>) -> Fallible<()> {
                 ex.set_report_url(&data.db, &report_url)?;
                 info!("report for the experiment {} generated successfully!", name);
 
-                let (mut regressed, mut fixed) = (0, 0);
-                res.crates.iter().for_each(|krate| {
-                    match krate.res {
-                        Comparison::Regressed => regressed += 1,
-                        Comparison::Fixed => fixed += 1,
-                        _ => (),
-                    };
-                });
+                let summary = if ex.is_baseline() {
+                    let (mut passed, mut failed, mut broken) = (0, 0, 0);
+                    res.crates.iter().for_each(|krate| {
+                        match krate.res {
+                            Comparison::SameTestPass | Comparison::SameTestSkipped => passed += 1,
+                            Comparison::SameBuildFail | Comparison::SameTestFail => failed += 1,
+                            Comparison::Broken => broken += 1,
+                            _ => (),
+                        };
+                    });
+                    format!(
+                        " {} passed, {} failed and {} broken ({} total)",
+                        passed,
+                        failed,
+                        broken,
+                        res.crates.len(),
+                    )
+                } else {
+                    let (mut regressed, mut fixed) = (0, 0);
+                    res.crates.iter().for_each(|krate| {
+                        match krate.res {
+                            Comparison::Regressed => regressed += 1,
+                            Comparison::Fixed => fixed += 1,
+                            _ => (),
+                        };
+                    });
+                    format!(
+                        " {} regressed and {} fixed ({} total)",
+                        regressed,
+                        fixed,
+                        res.crates.len(),
+                    )
+                };
 
                 if let Some(ref github_issue) = ex.github_issue {
                     Message::new()
                         .line("tada", format!("Experiment **`{}`** is completed!", name))
-                        .line(
-                            "bar_chart",
-                            format!(
-                                " {} regressed and {} fixed ({} total)",
-                                regressed,
-                                fixed,
-                                res.crates.len(),
-                            ),
-                        )
+                        .line("bar_chart", summary)
                         .line(
                             "newspaper",
                             format!("[Open the full report]({}).", report_url),
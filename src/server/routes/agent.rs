@@ -1,7 +1,8 @@
 use crate::agent::Capabilities;
+use crate::bisect;
 use crate::experiments::{Assignee, Experiment, Status};
 use crate::prelude::*;
-use crate::results::{DatabaseDB, EncodingType, ProgressData};
+use crate::results::{DatabaseDB, EncodingType, ProgressData, ReadResults};
 use crate::server::api_types::{AgentConfig, ApiResponse};
 use crate::server::auth::{auth_filter, AuthDetails, TokenType};
 use crate::server::messages::Message;
@@ -126,7 +127,12 @@ fn endpoint_next_experiment(data: Arc<Data>, auth: AuthDetails) -> Fallible<Resp
             }
         }
 
-        Some((ex.clone(), ex.get_uncompleted_crates(&data.db)?))
+        // Send the shas recorded by previous runs, to test the same commits of the git crates
+        let shas = DatabaseDB::new(&data.db)
+            .load_all_shas(&ex)?
+            .into_iter()
+            .collect::<Vec<_>>();
+        Some((ex.clone(), ex.get_uncompleted_crates(&data.db)?, shas))
     } else {
         None
     };
@@ -177,6 +183,14 @@ fn endpoint_error(
     let mut ex = Experiment::get(&data.db, &error.experiment_name)?
         .ok_or_else(|| err_msg("no experiment run by this agent"))?;
 
+    // The bisections tested by a failed probe are marked as failed by the reports generator,
+    // which also queues the next round of the other ones
+    if bisect::probe_parent(&data.db, &ex)?.is_some() {
+        ex.set_status(&data.db, Status::NeedsReport)?;
+        data.reports_worker.wake();
+        return Ok(ApiResponse::Success { result: true }.into_response()?);
+    }
+
     ex.set_status(&data.db, Status::Failed)?;
 
     if let Some(ref github_issue) = ex.github_issue {
@@ -39,8 +39,13 @@ impl ExperimentData {
                 Mode::BuildAndTest => "cargo test",
                 Mode::BuildOnly => "cargo build",
                 Mode::CheckOnly => "cargo check",
+                Mode::Doctest => "cargo test --doc",
+                Mode::BenchCheck => "cargo bench --no-run",
                 Mode::Clippy => "cargo clippy",
                 Mode::Rustdoc => "cargo doc",
+                Mode::Miri => "cargo miri test",
+                Mode::Rustfmt => "cargo fmt --check",
+                Mode::RustfmtIdempotence => "cargo fmt idempotence",
                 Mode::UnstableFeatures => "unstable features",
             },
             assigned_to: experiment.assigned_to.as_ref().map(|a| a.to_string()),
@@ -14,6 +14,41 @@ pub enum CommandParseError {
     UnknownKey(String),
 }
 
+/// A comma-separated list of toolchains, like `stable,beta,nightly`.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct ToolchainList(pub Vec<Toolchain>);
+
+impl std::str::FromStr for ToolchainList {
+    type Err = ::failure::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(ToolchainList(
+            input
+                .split(',')
+                .map(|tc| tc.parse())
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+/// A comma-separated list of targets, like `wasm32-unknown-unknown,i686-pc-windows-msvc`.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct TargetList(pub Vec<String>);
+
+impl std::str::FromStr for TargetList {
+    type Err = ::failure::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(TargetList(
+            input
+                .split(',')
+                .filter(|target| !target.is_empty())
+                .map(|target| target.to_string())
+                .collect(),
+        ))
+    }
+}
+
 macro_rules! generate_parser {
     (pub enum $enum:ident {
         $($command:expr => $variant:ident($var_struct:ident {
@@ -106,11 +141,15 @@ generate_parser!(pub enum Command {
         name: Option<String> = "name",
         start: Option<Toolchain> = "start",
         end: Option<Toolchain> = "end",
+        toolchains: Option<ToolchainList> = "toolchains",
+        baseline: Option<bool> = "baseline",
         mode: Option<Mode> = "mode",
         crates: Option<CrateSelect> = "crates",
         cap_lints: Option<CapLints> = "cap-lints",
         priority: Option<i32> = "p",
         ignore_blacklist: Option<bool> = "ignore-blacklist",
+        feature_matrix: Option<bool> = "feature-matrix",
+        targets: Option<TargetList> = "targets",
         assign: Option<Assignee> = "assign",
         requirement: Option<String> = "requirement",
     })
@@ -129,17 +168,24 @@ generate_parser!(pub enum Command {
         name: Option<String> = "name",
     })
 
+    "bisect" => Bisect(BisectArgs {
+        name: Option<String> = "name",
+    })
+
     "reload-acl" => ReloadACL(ReloadACLArgs {})
 
     _ => Edit(EditArgs {
         name: Option<String> = "name",
         start: Option<Toolchain> = "start",
         end: Option<Toolchain> = "end",
+        toolchains: Option<ToolchainList> = "toolchains",
         mode: Option<Mode> = "mode",
         crates: Option<CrateSelect> = "crates",
         cap_lints: Option<CapLints> = "cap-lints",
         priority: Option<i32> = "p",
         ignore_blacklist: Option<bool> = "ignore-blacklist",
+        feature_matrix: Option<bool> = "feature-matrix",
+        targets: Option<TargetList> = "targets",
         assign: Option<Assignee> = "assign",
         requirement: Option<String> = "requirement",
     })
@@ -1,14 +1,15 @@
 use crate::actions::{self, Action, ActionsCtx};
+use crate::bisect;
 use crate::db::{Database, QueryUtils};
 use crate::experiments::{CapLints, CrateSelect, Experiment, GitHubIssue, Mode, Status};
 use crate::prelude::*;
 use crate::server::github::{Issue, Repository};
 use crate::server::messages::{Label, Message};
 use crate::server::routes::webhooks::args::{
-    AbortArgs, EditArgs, RetryArgs, RetryReportArgs, RunArgs,
+    AbortArgs, BisectArgs, EditArgs, RetryArgs, RetryReportArgs, RunArgs,
 };
 use crate::server::Data;
-use crate::toolchain::Toolchain;
+use crate::toolchain::{FeatureSet, Toolchain};
 use rustwide::Toolchain as RustwideToolchain;
 
 pub fn ping(data: &Data, issue: &Issue) -> Fallible<()> {
@@ -28,9 +29,13 @@ pub fn run(
 ) -> Fallible<()> {
     let name = setup_run_name(&data.db, issue, args.name)?;
 
+    if args.toolchains.is_some() && (args.start.is_some() || args.end.is_some()) {
+        bail!("`toolchains` can't be used together with `start` or `end`");
+    }
+
     // Autodetect toolchains only if none of them was specified
     let (mut detected_start, mut detected_end, mut try_build) = (None, None, None);
-    if args.start.is_none() && args.end.is_none() {
+    if args.start.is_none() && args.end.is_none() && args.toolchains.is_none() {
         if let Some(build) =
             crate::server::try_builds::get_sha(&data.db, &repo.full_name, issue.number)?
         {
@@ -41,6 +46,8 @@ pub fn run(
                     alt: true,
                 },
                 rustflags: None,
+                features: FeatureSet::Default,
+                target: None,
                 ci_try: false,
             });
             detected_end = Some(Toolchain {
@@ -49,6 +56,8 @@ pub fn run(
                     alt: true,
                 },
                 rustflags: None,
+                features: FeatureSet::Default,
+                target: None,
                 ci_try: true,
             });
         }
@@ -57,16 +66,29 @@ pub fn run(
     // Make crater runs created via webhook require linux by default.
     let requirement = args.requirement.unwrap_or_else(|| "linux".to_string());
 
+    let toolchains: Vec<_> = if let Some(toolchains) = args.toolchains {
+        toolchains.0
+    } else {
+        let start = args
+            .start
+            .or(detected_start)
+            .ok_or_else(|| err_msg("missing start toolchain"))?;
+        std::iter::once(start)
+            .chain(args.end.or(detected_end))
+            .collect()
+    };
+
+    // Baseline experiments are opt-in, to avoid queueing a full run because of a missing toolchain
+    let baseline = args.baseline.unwrap_or(false);
+    if toolchains.len() == 1 && !baseline {
+        bail!("missing end toolchain (use `baseline=true` to only test the start toolchain)");
+    } else if toolchains.len() > 1 && baseline {
+        bail!("`baseline=true` can't be used together with more than one toolchain");
+    }
+
     actions::CreateExperiment {
         name: name.clone(),
-        toolchains: [
-            args.start
-                .or(detected_start)
-                .ok_or_else(|| err_msg("missing start toolchain"))?,
-            args.end
-                .or(detected_end)
-                .ok_or_else(|| err_msg("missing end toolchain"))?,
-        ],
+        toolchains,
         mode: args.mode.unwrap_or(Mode::BuildAndTest),
         crates: args.crates.unwrap_or(CrateSelect::Full),
         cap_lints: args.cap_lints.unwrap_or(CapLints::Forbid),
@@ -77,6 +99,8 @@ pub fn run(
             number: issue.number,
         }),
         ignore_blacklist: args.ignore_blacklist.unwrap_or(false),
+        feature_matrix: args.feature_matrix.unwrap_or(false),
+        targets: args.targets.map(|list| list.0).unwrap_or_default(),
         assign: args.assign,
         requirement: Some(requirement),
     }
@@ -106,12 +130,16 @@ pub fn edit(data: &Data, issue: &Issue, args: EditArgs) -> Fallible<()> {
 
     actions::EditExperiment {
         name: name.clone(),
-        toolchains: [args.start, args.end],
+        toolchains: args.toolchains.map(|list| list.0),
+        start: args.start,
+        end: args.end,
         crates: args.crates,
         mode: args.mode,
         cap_lints: args.cap_lints,
         priority: args.priority,
         ignore_blacklist: args.ignore_blacklist,
+        feature_matrix: args.feature_matrix,
+        targets: args.targets.map(|list| list.0),
         assign: args.assign,
         requirement: args.requirement,
     }
@@ -194,6 +222,31 @@ pub fn abort(data: &Data, issue: &Issue, args: AbortArgs) -> Fallible<()> {
     Ok(())
 }
 
+pub fn bisect(data: &Data, issue: &Issue, args: BisectArgs) -> Fallible<()> {
+    let name = get_name(&data.db, issue, args.name)?;
+
+    actions::CreateBisection { name: name.clone() }
+        .apply(&ActionsCtx::new(&data.db, &data.config))?;
+
+    // The agents run the bisections one round at a time, the next rounds are queued by the
+    // reports generator when the previous ones finish
+    if let Some(ex) = Experiment::get(&data.db, &name)? {
+        bisect::queue_probes(&data.db, &ex)?;
+    }
+
+    Message::new()
+        .line(
+            "mag",
+            format!(
+                "Bisection of the regressions found by **`{}`** queued.",
+                name
+            ),
+        )
+        .send(&issue.url, data)?;
+
+    Ok(())
+}
+
 pub fn reload_acl(data: &Data, issue: &Issue) -> Fallible<()> {
     data.acl.refresh_cache(&data.github)?;
 
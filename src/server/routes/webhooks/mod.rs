@@ -136,6 +136,10 @@ fn process_command(
                 commands::abort(data, issue, args)?;
             }
 
+            Command::Bisect(args) => {
+                commands::bisect(data, issue, args)?;
+            }
+
             Command::ReloadACL(_) => {
                 commands::reload_acl(data, issue)?;
             }
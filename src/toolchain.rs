@@ -12,6 +12,8 @@ pub(crate) static MAIN_TOOLCHAIN: Toolchain = Toolchain {
         name: Cow::Borrowed("stable"),
     },
     rustflags: None,
+    features: FeatureSet::Default,
+    target: None,
     ci_try: false,
 };
 
@@ -22,13 +24,52 @@ pub(crate) static TEST_TOOLCHAIN: Toolchain = Toolchain {
         name: Cow::Borrowed("beta"),
     },
     rustflags: None,
+    features: FeatureSet::Default,
+    target: None,
     ci_try: false,
 };
 
+string_enum!(pub enum FeatureSet {
+    Default => "default",
+    AllFeatures => "all",
+    NoDefaultFeatures => "no-default",
+});
+
+impl FeatureSet {
+    /// The feature sets tested by experiments with the feature matrix enabled.
+    pub const MATRIX: &'static [FeatureSet] = &[
+        FeatureSet::Default,
+        FeatureSet::AllFeatures,
+        FeatureSet::NoDefaultFeatures,
+    ];
+
+    /// The flags to pass to Cargo to enable this feature set.
+    pub(crate) fn cargo_args(self) -> &'static [&'static str] {
+        match self {
+            FeatureSet::Default => &[],
+            FeatureSet::AllFeatures => &["--all-features"],
+            FeatureSet::NoDefaultFeatures => &["--no-default-features"],
+        }
+    }
+}
+
+impl Default for FeatureSet {
+    fn default() -> Self {
+        FeatureSet::Default
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
 pub struct Toolchain {
     pub source: RustwideToolchain,
     pub rustflags: Option<String>,
+    /// The features crates are built with. Results with different feature sets are stored
+    /// separately, as the feature set is part of the name of the toolchain.
+    #[serde(default)]
+    pub features: FeatureSet,
+    /// The target crates are built for, or `None` for the host target.
+    #[serde(default)]
+    pub target: Option<String>,
     pub ci_try: bool,
 }
 
@@ -38,6 +79,22 @@ impl Toolchain {
 
         encode(&self.to_string(), &utils::FILENAME_ENCODE_SET).to_string()
     }
+
+    /// The same toolchain, building crates with another feature set.
+    pub fn with_features(&self, features: FeatureSet) -> Toolchain {
+        Toolchain {
+            features,
+            ..self.clone()
+        }
+    }
+
+    /// The same toolchain, building crates for another target.
+    pub fn with_target(&self, target: &str) -> Toolchain {
+        Toolchain {
+            target: Some(target.to_string()),
+            ..self.clone()
+        }
+    }
 }
 
 impl std::ops::Deref for Toolchain {
@@ -66,6 +123,14 @@ impl fmt::Display for Toolchain {
             write!(f, "+rustflags={}", flag)?;
         }
 
+        if self.features != FeatureSet::Default {
+            write!(f, "+features={}", self.features)?;
+        }
+
+        if let Some(ref target) = self.target {
+            write!(f, "+target={}", target)?;
+        }
+
         Ok(())
     }
 }
@@ -78,6 +143,8 @@ pub enum ToolchainParseError {
     InvalidSourceName(String),
     #[fail(display = "invalid toolchain flag: {}", _0)]
     InvalidFlag(String),
+    #[fail(display = "invalid feature set: {}", _0)]
+    InvalidFeatureSet(String),
 }
 
 impl FromStr for Toolchain {
@@ -119,6 +186,8 @@ impl FromStr for Toolchain {
         };
 
         let mut rustflags = None;
+        let mut features = FeatureSet::Default;
+        let mut target = None;
         for part in parts {
             if let Some(equal_idx) = part.find('=') {
                 let (flag, value_with_equal) = part.split_at(equal_idx);
@@ -130,6 +199,12 @@ impl FromStr for Toolchain {
 
                 match flag {
                     "rustflags" => rustflags = Some(value),
+                    "target" => target = Some(value),
+                    "features" => {
+                        features = value
+                            .parse()
+                            .map_err(|_| ToolchainParseError::InvalidFeatureSet(value))?
+                    }
                     unknown => return Err(ToolchainParseError::InvalidFlag(unknown.to_string())),
                 }
             } else {
@@ -140,6 +215,8 @@ impl FromStr for Toolchain {
         Ok(Toolchain {
             source,
             rustflags,
+            features,
+            target,
             ci_try,
         })
     }
@@ -147,7 +224,7 @@ impl FromStr for Toolchain {
 
 #[cfg(test)]
 mod tests {
-    use super::Toolchain;
+    use super::{FeatureSet, Toolchain};
     use rustwide::Toolchain as RustwideToolchain;
     use std::str::FromStr;
 
@@ -160,6 +237,8 @@ mod tests {
                     test_from_str!($str => Toolchain {
                         source: $source,
                         rustflags: None,
+                        features: FeatureSet::Default,
+                        target: None,
                         ci_try: $ci_try,
                     });
 
@@ -167,6 +246,29 @@ mod tests {
                     test_from_str!(concat!($str, "+rustflags=foo bar") => Toolchain {
                         source: $source,
                         rustflags: Some("foo bar".to_string()),
+                        features: FeatureSet::Default,
+                        target: None,
+                        ci_try: $ci_try,
+                    });
+                    test_from_str!(concat!($str, "+rustflags=foo bar+features=all") => Toolchain {
+                        source: $source,
+                        rustflags: Some("foo bar".to_string()),
+                        features: FeatureSet::AllFeatures,
+                        target: None,
+                        ci_try: $ci_try,
+                    });
+                    test_from_str!(concat!($str, "+features=no-default") => Toolchain {
+                        source: $source,
+                        rustflags: None,
+                        features: FeatureSet::NoDefaultFeatures,
+                        target: None,
+                        ci_try: $ci_try,
+                    });
+                    test_from_str!(concat!($str, "+features=all+target=wasm32-unknown-unknown") => Toolchain {
+                        source: $source,
+                        rustflags: None,
+                        features: FeatureSet::AllFeatures,
+                        target: Some("wasm32-unknown-unknown".to_string()),
                         ci_try: $ci_try,
                     });
                 )*
@@ -225,6 +327,8 @@ mod tests {
         assert!(Toolchain::from_str("foo#0000000000000000000000000000000000000000").is_err());
         assert!(Toolchain::from_str("stable+rustflags").is_err());
         assert!(Toolchain::from_str("stable+rustflags=").is_err());
+        assert!(Toolchain::from_str("stable+features=some").is_err());
+        assert!(Toolchain::from_str("stable+target=").is_err());
         assert!(Toolchain::from_str("stable+donotusethisflag=ever").is_err())
     }
 }
@@ -1,5 +1,6 @@
 use log::warn;
 use std::path::{Component, Path, PathBuf, Prefix, PrefixComponent};
+use walkdir::WalkDir;
 
 pub(crate) fn normalize_path(path: &Path) -> PathBuf {
     let mut p = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
@@ -36,6 +37,22 @@ pub(crate) fn normalize_path(path: &Path) -> PathBuf {
     p
 }
 
+/// The total size of the files inside a directory, in bytes.
+pub(crate) fn dir_size(path: &Path) -> Result<u64, walkdir::Error> {
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut size = 0;
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
 /// If a prefix uses the extended-length syntax (`\\?\`), return the equivalent version without it.
 ///
 /// Returns `None` if `prefix.kind().is_verbatim()` is `false`.
@@ -34,6 +34,13 @@ minicrater! {
         ..Default::default()
     },
 
+    baseline_small {
+        ex: "baseline",
+        crate_select: "demo",
+        toolchains: &["beta"],
+        ..Default::default()
+    },
+
     clippy_small {
         ex: "clippy",
         crate_select: "demo",